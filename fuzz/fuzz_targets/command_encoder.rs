@@ -0,0 +1,34 @@
+#![no_main]
+
+use elevator::lifty::Elevator;
+use elevator::types::cmd::Command;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (u8, u8)| {
+    let (variant, floor) = input;
+    let command = match variant % 12 {
+        0 => Command::MU,
+        1 => Command::MD,
+        2 => Command::S,
+        3 => Command::DO,
+        4 => Command::DC,
+        5 => Command::R,
+        6 => Command::CP(floor),
+        7 => Command::CU(floor),
+        8 => Command::CD(floor),
+        9 => Command::IU(floor),
+        10 => Command::ID(floor),
+        _ => Command::CI(floor),
+    };
+    let encoded = command.to_string();
+    assert!(encoded.starts_with(command.name()));
+    assert!(!encoded.contains(char::is_whitespace));
+    assert_eq!(Command::try_from(encoded.as_bytes()).ok(), Some(command));
+
+    // Lifty must recognise every command aimed at one of its floors.
+    if (1..=5).contains(&floor) {
+        let mut lifty = Elevator::new();
+        lifty.handle_command(&encoded);
+        assert_ne!(lifty.crash_reason, Some("Unrecognized command"));
+    }
+});
@@ -0,0 +1,22 @@
+#![no_main]
+
+use elevator::types::event::Event;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let events = Event::parse_datagram(data);
+    // One result per non-blank line, and each line parses the same on its
+    // own as inside the datagram.
+    let lines: Vec<_> = data
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.trim_ascii().is_empty())
+        .collect();
+    assert_eq!(events.len(), lines.len());
+    for (line, event) in lines.into_iter().zip(events) {
+        assert_eq!(Event::try_from(line).ok().as_ref(), event.as_ref().ok());
+        // Whatever decodes encodes back to the same message.
+        if let Ok(event) = event {
+            assert_eq!(event.to_bytes().as_deref(), Some(line.trim_ascii()));
+        }
+    }
+});
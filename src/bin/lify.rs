@@ -30,6 +30,7 @@ This is done by typing the following commands at the keyboard:
   Pn - Press button for floor n in the elevator car
   Un - Press up button on floor n
   Dn - Press down button on floor n
+  Wn - Set the load in the car to n percent of capacity
 
 Sadly, I don't have any brains of my own to know what to do
 when a button is pressed.  However, I can interact with a
@@ -48,6 +49,7 @@ I will send the following event messages to the controller:
   On - Door open on floor n (doors have fully opened)
   Cn - Door closed on floor n (now safe to move)
   Kn - Key switch changed to position n
+  Wn - Load sensor reads n percent of the car's capacity
 
 I understand the following commands from the controller
 
@@ -77,416 +79,17 @@ crash.  Good luck!
 
 */
 
-// This is a single-file Rust program with no dependencies.
-// Compile using `rustc lifty.rs`.
+// The hardware model lives in the `elevator::lifty` module so the model
+// checker can drive it directly; this file is the network runtime around it.
+
+use elevator::lifty::{Elevator, TICK_INTERVAL};
+use elevator::passengers::{Passengers, Traffic};
+use elevator::types::event::Event;
 
 // Network ports for myself and the control program.
 const MY_ADDRESS: &str = "127.0.0.1:10000";
 const CONTROL_ADDRESS: &str = "127.0.0.1:11000";
 
-// Internal timing
-const TICKS_PER_FLOOR: usize = 40;
-const TICKS_FOR_DOOR: usize = 20;
-const APPROACH_TICKS: usize = 10;
-const TICK_INTERVAL: u64 = 100;
-
-// Turn this on if you want Lifty to be super picky or
-// if you're looking for ways to deduct grading points.
-const PEDANTIC: bool = false;
-
-// Hoist motor status
-#[derive(Debug, Clone, PartialEq)]
-enum Motor {
-    Up,
-    Down,
-    Off,
-}
-
-// Door status
-#[derive(Debug, Clone, PartialEq)]
-enum Door {
-    Opening,
-    Open,
-    Closing,
-    Closed,
-}
-
-// Direction indicators
-#[derive(Debug, Clone, PartialEq)]
-enum Indicator {
-    Up,
-    Down,
-    Off,
-}
-
-#[derive(Debug)]
-struct Elevator {
-    pub floor: usize,
-    pub panel_buttons: [bool; 5], // Buttons in the car
-    pub up_buttons: [bool; 5],    // Up buttons in the building
-    pub down_buttons: [bool; 5],  // Down buttons in the building
-    pub indicator: Indicator,     // Indicator light status
-    pub indicator_floor: usize,
-    pub clock: usize,
-    pub motor: Motor,
-    pub door: Door,
-    pub stopping: bool,
-    pub crashed: bool,
-    pub key: usize, // Key switch setting
-}
-
-impl Elevator {
-    fn new() -> Elevator {
-        Elevator {
-            floor: 1,
-            panel_buttons: [false, false, false, false, false],
-            up_buttons: [false, false, false, false, false],
-            down_buttons: [false, false, false, false, false],
-            indicator: Indicator::Off,
-            indicator_floor: 1,
-            clock: 0,
-            motor: Motor::Off,
-            door: Door::Closed,
-            stopping: false,
-            crashed: false,
-            key: 0,
-        }
-    }
-
-    fn reset(&mut self) {
-        self.floor = 1;
-        self.panel_buttons = [false, false, false, false, false];
-        self.up_buttons = [false, false, false, false, false];
-        self.down_buttons = [false, false, false, false, false];
-        self.indicator = Indicator::Off;
-        self.indicator_floor = 1;
-        self.clock = 0;
-        self.motor = Motor::Off;
-        self.door = Door::Closed;
-        self.stopping = false;
-        self.crashed = false;
-    }
-
-    fn crash(&mut self, reason: &str) {
-        println!("\nCRASH! : {reason}");
-        self.crashed = true;
-    }
-
-    fn as_string(&self) -> String {
-        let mut ps = String::from("P:");
-        for (n, floor) in self.panel_buttons.iter().enumerate() {
-            if *floor {
-                ps.push(char::from_u32(49 + n as u32).unwrap());
-            } else {
-                ps.push('-');
-            }
-        }
-        let mut us = String::from("U:");
-        for (n, floor) in self.up_buttons.iter().enumerate() {
-            if *floor {
-                us.push(char::from_u32(49 + n as u32).unwrap());
-            } else {
-                us.push('-');
-            }
-        }
-        let mut ds = String::from("D:");
-        for (n, floor) in self.down_buttons.iter().enumerate() {
-            if *floor {
-                ds.push(char::from_u32(49 + n as u32).unwrap());
-            } else {
-                ds.push('-');
-            }
-        }
-        let indicator = if self.indicator_floor == self.floor {
-            match self.indicator {
-                Indicator::Up => "^^",
-                Indicator::Down => "vv",
-                Indicator::Off => "--",
-            }
-        } else {
-            "--"
-        };
-        let status = if self.crashed {
-            "CRASH"
-        } else if self.stopping && self.clock >= (TICKS_PER_FLOOR - APPROACH_TICKS) {
-            "STOPPING"
-        } else if self.motor == Motor::Up {
-            "UP"
-        } else if self.motor == Motor::Down {
-            "DOWN"
-        } else if self.door == Door::Opening {
-            "OPENING"
-        } else if self.door == Door::Open {
-            "OPEN"
-        } else if self.door == Door::Closing {
-            "CLOSING"
-        } else if self.door == Door::Closed {
-            "CLOSED"
-        } else {
-            panic!("Can't determine status")
-        };
-        let key = if self.key > 0 {
-            format!(" | K{}", self.key)
-        } else {
-            String::from(" ")
-        };
-        format!(
-            "[ FLOOR {} | {status:8} {indicator} | {ps} | {us} | {ds}{key} ]",
-            self.floor
-        )
-    }
-
-    fn set_panel_button(&mut self, floor: usize) {
-        self.panel_buttons[floor - 1] = true;
-    }
-
-    fn clear_panel_button(&mut self, floor: usize) {
-        if PEDANTIC && !self.panel_buttons[floor - 1] {
-            self.crash("panel button not previously set");
-        } else {
-            self.panel_buttons[floor - 1] = false;
-        }
-    }
-
-    fn set_up_button(&mut self, floor: usize) {
-        self.up_buttons[floor - 1] = true;
-    }
-
-    fn clear_up_button(&mut self, floor: usize) {
-        if PEDANTIC && !self.up_buttons[floor - 1] {
-            self.crash("up button not previously set");
-        } else {
-            self.up_buttons[floor - 1] = false;
-        }
-    }
-
-    fn set_down_button(&mut self, floor: usize) {
-        self.down_buttons[floor - 1] = true;
-    }
-
-    fn clear_down_button(&mut self, floor: usize) {
-        if PEDANTIC && !self.down_buttons[floor - 1] {
-            self.crash("down button not previously set");
-        } else {
-            self.down_buttons[floor - 1] = false;
-        }
-    }
-
-    fn set_indicator(&mut self, floor: usize, status: Indicator) {
-        if self.indicator != Indicator::Off && status != Indicator::Off {
-            self.crash("direction indicator already illuminated");
-        } else if PEDANTIC && self.indicator == Indicator::Off && status == Indicator::Off {
-            self.crash("direction indicator already off");
-        } else {
-            self.indicator = status;
-            self.indicator_floor = floor;
-        }
-    }
-
-    fn set_motor(&mut self, status: Motor) {
-        if self.door != Door::Closed {
-            self.crash("motor command received while doors open");
-            return;
-        }
-        if self.motor == Motor::Up && status == Motor::Down {
-            self.crash("violent direction switch (up->down)");
-            return;
-        }
-        if self.motor == Motor::Down && status == Motor::Up {
-            self.crash("violent direction switch (down->up)");
-            return;
-        }
-        if self.motor != status {
-            self.motor = status;
-            self.clock = 0;
-        } else if status == Motor::Up {
-            self.crash("already moving up");
-        } else if status == Motor::Down {
-            self.crash("already moving down");
-        }
-    }
-
-    fn set_door(&mut self, status: Door) {
-        if self.motor != Motor::Off {
-            self.crash("door command received while moving");
-            return;
-        }
-        if self.door == Door::Closing && status != Door::Closed {
-            self.crash("door command received while closing");
-            return;
-        }
-        if self.door == Door::Opening && status != Door::Open {
-            self.crash("door command received while opening");
-            return;
-        }
-        if self.door == Door::Open && status == Door::Opening {
-            self.crash("door already open");
-            return;
-        }
-        if self.door == Door::Closed && status == Door::Closing {
-            self.crash("door already closed");
-            return;
-        }
-        self.door = status;
-        self.clock = 0;
-    }
-
-    fn handle_command(&mut self, cmd: &str) -> Option<String> {
-        if cmd == "R" {
-            self.reset();
-            return None;
-        }
-        if self.crashed {
-            return None;
-        }
-        match cmd {
-            // Button presses
-            "P1" | "P2" | "P3" | "P4" | "P5" => {
-                self.set_panel_button(cmd[1..].parse().unwrap());
-                Some(cmd.to_string())
-            }
-            "U1" | "U2" | "U3" | "U4" => {
-                self.set_up_button(cmd[1..].parse().unwrap());
-                Some(cmd.to_string())
-            }
-            "U5" | "CU5" => {
-                self.crash("No up button on top floor");
-                None
-            }
-            "D2" | "D3" | "D4" | "D5" => {
-                self.set_down_button(cmd[1..].parse().unwrap());
-                Some(cmd.to_string())
-            }
-            "D1" | "CD1" => {
-                self.crash("No down button on bottom floor");
-                None
-            }
-            // Clear buttons
-            "CP1" | "CP2" | "CP3" | "CP4" | "CP5" => {
-                self.clear_panel_button(cmd[2..].parse().unwrap());
-                None
-            }
-            "CU1" | "CU2" | "CU3" | "CU4" => {
-                self.clear_up_button(cmd[2..].parse().unwrap());
-                None
-            }
-            "CD2" | "CD3" | "CD4" | "CD5" => {
-                self.clear_down_button(cmd[2..].parse().unwrap());
-                None
-            }
-            // Direction indicator lights
-            "IU1" | "IU2" | "IU3" | "IU4" => {
-                self.set_indicator(cmd[2..].parse().unwrap(), Indicator::Up);
-                None
-            }
-            "IU5" => {
-                self.crash("No up indicator light on top floor");
-                None
-            }
-            "ID2" | "ID3" | "ID4" | "ID5" => {
-                self.set_indicator(cmd[2..].parse().unwrap(), Indicator::Down);
-                None
-            }
-            "ID1" => {
-                self.crash("No down indicator light on bottom floor");
-                None
-            }
-            "CI1" | "CI2" | "CI3" | "CI4" | "CI5" => {
-                self.set_indicator(cmd[2..].parse().unwrap(), Indicator::Off);
-                None
-            }
-            // Motor (from control)
-            "MU" => {
-                self.set_motor(Motor::Up);
-                None
-            }
-            "MD" => {
-                self.set_motor(Motor::Down);
-                None
-            }
-            "S" => {
-                if self.stopping {
-                    self.crash("Already made a request to stop");
-                } else if self.motor != Motor::Off {
-                    // If we can safely stop we will.
-                    if self.clock <= TICKS_PER_FLOOR - APPROACH_TICKS {
-                        self.stopping = true;
-                    }
-                } else {
-                    self.crash("Request to stop, but not moving");
-                }
-                None
-            }
-            // Door commands (from control)
-            "DO" => {
-                self.set_door(Door::Opening);
-                None
-            }
-            "DC" => {
-                self.set_door(Door::Closing);
-                None
-            }
-
-            // Key switch
-            "K0" | "K1" | "K2" => {
-                self.key = cmd[1..].parse().unwrap();
-                Some(cmd.to_string())
-            }
-
-            // Clock
-            "T" => self.handle_tick(),
-            _ => {
-                self.crash("Unrecognized command");
-                None
-            }
-        }
-    }
-
-    fn handle_tick(&mut self) -> Option<String> {
-        self.clock += 1;
-        if self.motor == Motor::Up {
-            if self.floor >= 5 {
-                self.crash("Hit the roof!");
-            } else if self.clock == (TICKS_PER_FLOOR - APPROACH_TICKS) {
-                return Some(format!("A{}", self.floor + 1));
-            } else if self.clock >= TICKS_PER_FLOOR {
-                self.floor += 1;
-                self.clock = 0;
-                if self.stopping {
-                    self.set_motor(Motor::Off);
-                    self.stopping = false;
-                    return Some(format!("S{}", self.floor));
-                }
-            }
-        } else if self.motor == Motor::Down {
-            if self.floor <= 1 {
-                self.crash("Hit the ground!");
-            } else if self.clock == (TICKS_PER_FLOOR - APPROACH_TICKS) {
-                return Some(format!("A{}", self.floor - 1));
-            } else if self.clock >= TICKS_PER_FLOOR {
-                self.floor -= 1;
-                self.clock = 0;
-                if self.stopping {
-                    self.set_motor(Motor::Off);
-                    self.stopping = false;
-                    return Some(format!("S{}", self.floor));
-                }
-            }
-        } else if self.door == Door::Closing {
-            if self.clock > TICKS_FOR_DOOR {
-                self.set_door(Door::Closed);
-                return Some(format!("C{}", self.floor));
-            }
-        } else if self.door == Door::Opening {
-            if self.clock > TICKS_FOR_DOOR {
-                self.set_door(Door::Open);
-                return Some(format!("O{}", self.floor));
-            }
-        }
-        None
-    }
-}
-
 // Runtime environment for the simulator
 
 use std::io;
@@ -523,7 +126,7 @@ fn read_socket(address: &str, tx: Sender<Command>) -> ! {
         let mut buf = [0; 2000];
         match socket.recv_from(&mut buf) {
             Ok((n, _)) => {
-                let cmds = String::from_utf8((&buf[0..n]).to_vec()).unwrap();
+                let cmds = String::from_utf8(buf[0..n].to_vec()).unwrap();
                 for cmd in cmds.lines() {
                     tx.send(Command::Internal(cmd.to_string())).unwrap();
                 }
@@ -543,8 +146,35 @@ fn spawn_threads() -> Receiver<Command> {
     rx
 }
 
+const DEFAULT_PER_MINUTE: f64 = 6.0;
+
+/// `lify [poisson|up-peak|interfloor] [PER_MINUTE] [SEED]` adds simulated
+/// passengers to the keyboard.
+fn passengers() -> Option<Passengers> {
+    let usage = |e: String| -> ! {
+        eprintln!("{e}\nusage: lify [poisson|up-peak|interfloor] [PER_MINUTE] [SEED]");
+        std::process::exit(2);
+    };
+    let mut args = std::env::args().skip(1);
+    let traffic: Traffic = args.next()?.parse().unwrap_or_else(|e| usage(e));
+    let per_minute = args.next().map_or(Ok(DEFAULT_PER_MINUTE), |arg| {
+        arg.parse()
+            .ok()
+            .filter(|rate: &f64| rate.is_finite() && *rate >= 0.0)
+            .ok_or_else(|| format!("{arg:?} is not a rate"))
+    });
+    let seed = args.next().map_or(Ok(1), |arg| {
+        arg.parse().map_err(|_| format!("{arg:?} is not a seed"))
+    });
+    let per_minute = per_minute.unwrap_or_else(|e| usage(e));
+    let seed = seed.unwrap_or_else(|e| usage(e));
+    println!("Simulating {traffic:?} traffic, {per_minute} passengers a minute.\n");
+    Some(Passengers::new(traffic, per_minute, seed))
+}
+
 fn main() {
     let mut elev = Elevator::new();
+    let mut passengers = passengers();
     let command_channel = spawn_threads();
     let mut last = String::new();
     let out_socket = UdpSocket::bind("0.0.0.0:0").unwrap();
@@ -553,7 +183,8 @@ fn main() {
     println!("I'm just hardware, but you can press my buttons\n(type below and hit return):\n");
     println!("    Pn  - Floor n button on panel inside car");
     println!("    Un  - Up button on floor n");
-    println!("    Dn  - Down button on floor n\n");
+    println!("    Dn  - Down button on floor n");
+    println!("    Wn  - Load in the car, n percent of capacity\n");
     println!("If something goes wrong, I'll crash and you'll have to call");
     println!("maintenance to restart the elevator control program.\n");
 
@@ -562,7 +193,7 @@ fn main() {
         let es = elev.as_string();
         if es != last {
             if print_newline {
-                print!("\n");
+                println!();
             }
             print!("{} : ", es);
             std::io::stdout().flush().unwrap();
@@ -587,13 +218,35 @@ fn main() {
                         cmd
                     }
                 };
-                if cmd.len() > 0 {
-                    if let Some(outcmd) = elev.handle_command(&cmd) {
-                        out_socket
-                            .send_to(outcmd.as_bytes(), CONTROL_ADDRESS)
-                            .expect("couldn't send data");
+                let was_crashed = elev.crashed;
+                if !cmd.is_empty()
+                    && let Some(event) = elev.handle_command(&cmd)
+                    && let Some(outcmd) = event.to_bytes()
+                {
+                    out_socket
+                        .send_to(&outcmd, CONTROL_ADDRESS)
+                        .expect("couldn't send data");
+                }
+                if cmd == "T"
+                    && let Some(passengers) = &mut passengers
+                {
+                    let delivered = passengers.delivered.len();
+                    for press in passengers.tick(&elev).iter().filter_map(Event::to_bytes) {
+                        if let Some(event) = elev.handle_command(&String::from_utf8_lossy(&press))
+                            && let Some(outcmd) = event.to_bytes()
+                        {
+                            out_socket
+                                .send_to(&outcmd, CONTROL_ADDRESS)
+                                .expect("couldn't send data");
+                        }
+                    }
+                    if passengers.delivered.len() != delivered {
+                        println!("{}", passengers.summary());
                     }
                 }
+                if !was_crashed && let Some(reason) = elev.crash_reason {
+                    println!("\nCRASH! : {reason}");
+                }
             }
             Err(e) => {
                 println!("{:?}", e);
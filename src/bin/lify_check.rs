@@ -0,0 +1,35 @@
+use elevator::model_check::explore;
+
+const MIN_FLOOR: u8 = 1;
+const MAX_FLOOR: u8 = 5;
+const DEFAULT_DEPTH: usize = 16;
+const DEFAULT_PRESSES: usize = 3;
+
+fn arg(position: usize, default: usize) -> usize {
+    match std::env::args().nth(position) {
+        Some(arg) => arg.parse().unwrap_or_else(|_| {
+            eprintln!("usage: lify_check [DEPTH] [MAX_PRESSES]");
+            std::process::exit(2);
+        }),
+        None => default,
+    }
+}
+
+fn main() {
+    let depth = arg(1, DEFAULT_DEPTH);
+    let max_presses = arg(2, DEFAULT_PRESSES);
+
+    // Controller logging goes to stdout; the verdict goes to stderr.
+    let report = explore(MIN_FLOOR, MAX_FLOOR, depth, max_presses);
+    match report.counterexample {
+        Some(counterexample) => {
+            eprintln!("{counterexample}");
+            eprintln!("after exploring {} states", report.states);
+            std::process::exit(1);
+        }
+        None => eprintln!(
+            "no crash or stuck state within {depth} steps and {max_presses} presses ({} states)",
+            report.states
+        ),
+    }
+}
@@ -1,84 +1,226 @@
 use anyhow::Result;
-use elevator::context::{ElevatorContext, Location};
+use elevator::car;
+use elevator::checkpoint::{self, Checkpoint};
+use elevator::command_channel::{self, COMMAND_CHANNEL_CAPACITY};
+use elevator::config::{ControllerConfig, FlushPolicy, Startup};
+use elevator::console::Console;
+use elevator::context::ElevatorContext;
+use elevator::error::{Error, ErrorClass};
+use elevator::metrics::{self, Metrics};
 use elevator::services::controller::ControllerService;
 use elevator::services::scheduler::SchedulerEventLayer;
 use elevator::services::udp_event::UdpEventLayer;
 use elevator::strategies::scan::ScanStrategy;
-use elevator::transition::{ElevatorState, IntoBoxedTransition, PreStart};
-use elevator::types::cmd::Command;
+use elevator::strategies::switchable::{ElevatorStrategy, SwitchableStrategy};
+use elevator::transition::{ElevatorState, PreStart};
+use elevator::types::event::Event;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::UdpSocket;
-use tokio::sync::Mutex;
 use tower::{Service, ServiceBuilder, ServiceExt};
 
 const UDP_MAX_SIZE: usize = 65535;
 const CONTROL_ADDRESS: &str = "127.0.0.1:11000";
 const LIFTY_ADDRESS: &str = "127.0.0.1:10000";
+const METRICS_ADDRESS: &str = "127.0.0.1:9100";
 const MIN_FLOOR: u8 = 1;
 const MAX_FLOOR: u8 = 5;
-#[allow(dead_code)]
 const MIN_KEY: u8 = 0;
-#[allow(dead_code)]
 const MAX_KEY: u8 = 3;
+const EVENT_QUEUE_CAPACITY: usize = 1024;
+const CONSOLE_REFRESH: Duration = Duration::from_millis(200);
 
 pub struct ElevatorApp {
     socket: Arc<UdpSocket>,
+    console: bool,
 }
 
 impl ElevatorApp {
-    pub async fn new() -> Result<Self> {
+    pub async fn new(console: bool) -> Result<Self> {
         let socket = Arc::new(UdpSocket::bind(CONTROL_ADDRESS).await?);
         println!("Listening on {CONTROL_ADDRESS}");
-        Ok(Self { socket })
+        Ok(Self { socket, console })
     }
 
-    pub async fn run(self) -> Result<()> {
-        // Initialize the channel and state
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<Command>();
-        let elevator_context = Arc::new(Mutex::new(ElevatorContext {
-            current_location: Location::AtFloor(MIN_FLOOR),
-            min_floor: MIN_FLOOR,
-            max_floor: MAX_FLOOR,
-            direction_up: true,
-            ..Default::default()
-        }));
+    pub async fn run(
+        self,
+        config: ControllerConfig,
+        flush: FlushPolicy,
+        startup: Startup,
+        checkpoint_path: Option<PathBuf>,
+    ) -> Result<()> {
+        let checkpoint = match &checkpoint_path {
+            Some(path) => Checkpoint::load(path, MIN_FLOOR, MAX_FLOOR)
+                .await
+                .unwrap_or_else(|e| {
+                    eprintln!("Ignoring checkpoint {}: {e}", path.display());
+                    None
+                }),
+            None => None,
+        };
+        let restored = checkpoint.is_some();
+        let strategy_name = checkpoint.as_ref().and_then(|c| c.strategy.clone());
 
+        // The car owns the state machine and context; it resets or takes over
+        // the hardware once the sender below is running, since sends wait
+        // until written.
+        let (tx, rx) = command_channel::channel(COMMAND_CHANNEL_CAPACITY);
         let prestart = ElevatorState::<PreStart>::new(tx);
-        let init = prestart.init().await?;
-        println!("Elevator controller initialized");
+        let car = match checkpoint {
+            Some(checkpoint) => car::restore(prestart, checkpoint, startup),
+            None => car::start(
+                prestart,
+                ElevatorContext::new(MIN_FLOOR, MAX_FLOOR),
+                startup,
+            ),
+        };
 
-        let boxed = init.boxed();
-        let state_machine = Arc::new(Mutex::new(Some(boxed)));
-        let scheduler_strategy = ScanStrategy::new(elevator_context.clone());
-        let scheduler = SchedulerEventLayer::new(scheduler_strategy, state_machine.clone());
-        let controller_service = ControllerService::new(state_machine, elevator_context);
+        let metrics = Arc::new(Metrics::default());
+        metrics::serve(METRICS_ADDRESS, metrics.clone(), car.clone()).await?;
 
+        let controller_service =
+            ControllerService::new(car.clone(), metrics.clone(), config.strict);
         let shared_socket = self.socket.clone();
         controller_service
-            .run_background(shared_socket, rx, LIFTY_ADDRESS)
+            .run_background(shared_socket, rx, LIFTY_ADDRESS, flush)
             .await?;
 
+        let snapshot = car.snapshot().await?;
+        println!("Elevator controller initialized");
+        metrics.enter_state(snapshot.state);
+
+        let mut strategies: HashMap<String, Arc<ElevatorStrategy>> = HashMap::new();
+        strategies.insert("scan".to_string(), Arc::new(ScanStrategy::new()));
+        let initial = strategy_name
+            .filter(|name| strategies.contains_key(name))
+            .unwrap_or_else(|| "scan".to_string());
+        let scheduler_strategy = SwitchableStrategy::new(strategies, &initial);
+        if let Some(path) = checkpoint_path {
+            checkpoint::spawn_saver(path, car.clone(), scheduler_strategy.clone());
+        }
+        let scheduler =
+            SchedulerEventLayer::new(scheduler_strategy, car.clone(), config, metrics.clone());
+        let homing = car.clone();
+        let mut console = self.console.then(|| {
+            Console::new(
+                MIN_FLOOR..=MAX_FLOOR,
+                MIN_KEY..MAX_KEY,
+                car,
+                scheduler.decisions(),
+            )
+        });
+        let mut console_rx = match console {
+            Some(_) => Console::spawn_reader(),
+            None => tokio::sync::mpsc::unbounded_channel().1,
+        };
+
         let mut svc = ServiceBuilder::new()
-            .layer(UdpEventLayer)
+            .layer(UdpEventLayer::new(metrics, MIN_FLOOR..=MAX_FLOOR))
             .layer(scheduler)
             .service(controller_service);
 
+        // The car handles its events in order on its own task, so the socket
+        // is drained even while a plan's steps run.
+        let (events, mut queue) = tokio::sync::mpsc::channel::<Ingress>(EVENT_QUEUE_CAPACITY);
+        let mut car = tokio::spawn(async move {
+            while let Some(ingress) = queue.recv().await {
+                let result = match ingress {
+                    Ingress::Datagram(raw) => {
+                        ServiceExt::<&[u8]>::ready(&mut svc).await?;
+                        svc.call(raw.as_slice()).await
+                    }
+                    Ingress::Injected(event) => {
+                        ServiceExt::<Event>::ready(&mut svc).await?;
+                        svc.call(event).await
+                    }
+                };
+                react(result)?;
+            }
+            Ok::<(), anyhow::Error>(())
+        });
+
+        // Calls carried over from the checkpoint wait for no new event.
+        if restored {
+            events.send(Ingress::Injected(Event::Restored)).await?;
+        }
+        if let Startup::Home { .. } = startup {
+            let events = events.clone();
+            tokio::spawn(async move {
+                match car::reset_if_lost(&homing).await {
+                    // Neither do the calls held while homing.
+                    Ok(true) => {
+                        let _ = events.send(Ingress::Injected(Event::Restored)).await;
+                    }
+                    Ok(false) => {}
+                    Err(e) => eprintln!("Cannot recover from homing: {e}"),
+                }
+            });
+        }
+
+        let mut refresh = tokio::time::interval(CONSOLE_REFRESH);
         let mut buf = vec![0u8; UDP_MAX_SIZE];
         loop {
-            let (len, addr) = self.socket.recv_from(&mut buf).await?;
-            println!("Got UDP packet from {addr}");
-
-            let raw = &buf[..len];
-            svc.ready().await?;
-            if svc.call(raw).await.is_err() {
-                eprintln!("Service error");
+            let ingress = tokio::select! {
+                received = self.socket.recv_from(&mut buf) => {
+                    let (len, addr) = received?;
+                    println!("Got UDP packet from {addr}");
+                    Ingress::Datagram(buf[..len].to_vec())
+                }
+                Some(line) = console_rx.recv() => {
+                    match console.as_mut().and_then(|c| c.handle_line(&line)) {
+                        Some(event) => Ingress::Injected(event),
+                        None => continue,
+                    }
+                }
+                _ = refresh.tick(), if console.is_some() => {
+                    if let Some(console) = console.as_mut() {
+                        console.refresh().await;
+                    }
+                    continue;
+                }
+                stopped = &mut car => return stopped?,
+            };
+            if events.send(ingress).await.is_err() {
+                return car.await?;
             }
         }
     }
 }
 
+/// Input for the car, in arrival order.
+enum Ingress {
+    Datagram(Vec<u8>),
+    Injected(Event),
+}
+
+/// Decides whether the controller can keep running after a failed event.
+fn react(result: Result<(), Error>) -> Result<()> {
+    let Err(e) = result else {
+        return Ok(());
+    };
+    match e.class() {
+        ErrorClass::Drop => eprintln!("Dropped: {e}"),
+        // The scheduler already retried it; the event is given up on.
+        ErrorClass::Retry => eprintln!("Gave up after retries: {e}"),
+        ErrorClass::Fault => eprintln!("Fault: {e}"),
+        ErrorClass::Escalate => {
+            eprintln!("Unrecoverable: {e}");
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let app = ElevatorApp::new().await?;
-    app.run().await
+    let console = std::env::args().any(|arg| arg == "--console");
+    let config = ControllerConfig::from_env()?;
+    let flush = FlushPolicy::from_env()?;
+    let startup = Startup::from_env()?;
+    // Checkpointing is off unless `ELEVATOR_CHECKPOINT` names a file.
+    let checkpoint = std::env::var_os("ELEVATOR_CHECKPOINT").map(PathBuf::from);
+    let app = ElevatorApp::new(console).await?;
+    app.run(config, flush, startup, checkpoint).await
 }
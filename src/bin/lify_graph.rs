@@ -0,0 +1,12 @@
+use elevator::transition_table::{to_dot, to_mermaid};
+
+fn main() {
+    match std::env::args().nth(1).as_deref() {
+        Some("dot") | None => print!("{}", to_dot()),
+        Some("mermaid") => print!("{}", to_mermaid()),
+        Some(other) => {
+            eprintln!("unknown format {other}, expected dot or mermaid");
+            std::process::exit(2);
+        }
+    }
+}
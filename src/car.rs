@@ -0,0 +1,267 @@
+use crate::checkpoint::Checkpoint;
+use crate::config::Startup;
+use crate::context::{CarContext, ElevatorContext};
+use crate::error::{Anomaly, Error, Result};
+use crate::transition::{
+    BoxedTransition, ElevatorState, IntoBoxedTransition, PreStart, State, TransitionError,
+};
+use crate::transition_table::next_state;
+use crate::types::sched_events::Action;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{mpsc, oneshot};
+
+/// Messages the car may queue before senders start waiting.
+const MAILBOX_CAPACITY: usize = 64;
+
+/// How long a homing car may take to approach its first floor. From rest
+/// Lifty approaches the next floor after 3s.
+pub const HOMING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A consistent view of the car, taken between two messages.
+#[derive(Debug, Clone)]
+pub struct Snapshot<C = ElevatorContext> {
+    pub state: State,
+    pub context: C,
+}
+
+/// What applying an action did. `result` holds the state the car ended up
+/// in, or why the transition failed; either way the car still has its machine.
+#[derive(Debug)]
+pub struct Applied {
+    pub from: State,
+    /// Whether the transition table has an edge for the action.
+    pub legal: bool,
+    pub result: Result<State>,
+}
+
+type Update<C> = Box<dyn FnOnce(&mut C, State) + Send>;
+
+enum Message<C> {
+    Apply {
+        action: Action,
+        /// Refuse actions without an edge instead of handing them to the machine.
+        strict: bool,
+        reply: oneshot::Sender<Applied>,
+    },
+    Snapshot(oneshot::Sender<Snapshot<C>>),
+    Update(Update<C>),
+}
+
+/// Spawns the car, which resets or homes the hardware before serving
+/// messages. If that fails the car stops and every handle call fails.
+pub fn start<C: CarContext + Clone>(
+    prestart: ElevatorState<PreStart>,
+    mut context: C,
+    startup: Startup,
+) -> CarHandle<C> {
+    let (tx, rx) = mpsc::channel(MAILBOX_CAPACITY);
+    tokio::spawn(async move {
+        match cold_start(prestart, &mut context, startup).await {
+            Ok(machine) => run(machine, context, rx).await,
+            Err(e) => eprintln!("Car failed to initialize: {e}"),
+        }
+    });
+    CarHandle(tx)
+}
+
+async fn cold_start<C: CarContext>(
+    prestart: ElevatorState<PreStart>,
+    context: &mut C,
+    startup: Startup,
+) -> Result<BoxedTransition<C>> {
+    match startup {
+        Startup::Reset => Ok(prestart.init().await?.boxed()),
+        Startup::Home { up } => {
+            context.set_direction(up);
+            Ok(prestart.home(up).await?.boxed())
+        }
+    }
+}
+
+/// Resets a car that is still homing after `HOMING_TIMEOUT`. One recalled
+/// past the end of the shaft or with its door open never approaches a
+/// floor: Lifty crashed instead, and only `R` brings it back. The calls held
+/// while homing are queued again. Returns whether the car was reset.
+pub async fn reset_if_lost(car: &CarHandle) -> Result<bool> {
+    tokio::time::sleep(HOMING_TIMEOUT).await;
+    let lost = car
+        .update(|ctx, state| (state == State::Homing).then(|| ctx.pending()))
+        .await?;
+    let Some(calls) = lost else {
+        return Ok(false);
+    };
+    eprintln!("Homing found no floor in {HOMING_TIMEOUT:?}, resetting.");
+    car.apply(Action::Reset, false).await?.result?;
+    car.update(move |ctx, _| {
+        for (floor, call) in calls {
+            ctx.enqueue_call(floor, call);
+        }
+    })
+    .await?;
+    Ok(true)
+}
+
+/// Spawns the car from a checkpoint. A warm checkpoint is taken over as it
+/// is; otherwise the car starts as in `start`, but the pending calls survive
+/// with where they were made and are queued again.
+pub fn restore(
+    prestart: ElevatorState<PreStart>,
+    checkpoint: Checkpoint,
+    startup: Startup,
+) -> CarHandle {
+    let (tx, rx) = mpsc::channel(MAILBOX_CAPACITY);
+    tokio::spawn(async move {
+        let warm = checkpoint.is_warm(SystemTime::now());
+        let calls = checkpoint.pending_calls();
+        let Checkpoint {
+            state, mut context, ..
+        } = checkpoint;
+        let machine = if warm {
+            println!("Warm start in {state:?} at {:?}", context.position);
+            prestart.resume(state).await
+        } else {
+            println!("Checkpoint is not safe to resume, resetting with {calls:?} pending");
+            context.reset();
+            // A homing car holds its calls until it knows where it is.
+            for (floor, call) in calls {
+                match startup {
+                    Startup::Reset => context.enqueue_call(floor, call),
+                    Startup::Home { .. } => context.hold_call(floor, call),
+                }
+            }
+            cold_start(prestart, &mut context, startup).await
+        };
+        match machine {
+            Ok(machine) => run(machine, context, rx).await,
+            Err(e) => eprintln!("Car failed to initialize: {e}"),
+        }
+    });
+    CarHandle(tx)
+}
+
+/// Spawns a car around a machine that is already running.
+pub fn spawn<C: CarContext + Clone>(machine: BoxedTransition<C>, context: C) -> CarHandle<C> {
+    let (tx, rx) = mpsc::channel(MAILBOX_CAPACITY);
+    tokio::spawn(run(machine, context, rx));
+    CarHandle(tx)
+}
+
+/// The car task: the only owner of the state machine and the context, which
+/// everyone else reaches through a `CarHandle`, one message at a time.
+async fn run<C: CarContext + Clone>(
+    mut machine: BoxedTransition<C>,
+    mut context: C,
+    mut rx: mpsc::Receiver<Message<C>>,
+) {
+    while let Some(message) = rx.recv().await {
+        match message {
+            Message::Apply {
+                action,
+                strict,
+                reply,
+            } => {
+                let applied;
+                (machine, applied) = apply(machine, &mut context, action, strict).await;
+                let _ = reply.send(applied);
+            }
+            Message::Snapshot(reply) => {
+                let _ = reply.send(Snapshot {
+                    state: machine.state(),
+                    context: context.clone(),
+                });
+            }
+            Message::Update(update) => update(&mut context, machine.state()),
+        }
+    }
+}
+
+/// Success and failure both hand a machine back, so the car is never left
+/// without one.
+async fn apply<C: CarContext>(
+    machine: BoxedTransition<C>,
+    context: &mut C,
+    action: Action,
+    strict: bool,
+) -> (BoxedTransition<C>, Applied) {
+    let from = machine.state();
+    let legal = next_state(from, action).is_some();
+    if strict && !legal {
+        let error = Error::Anomaly(Anomaly::IllegalTransition {
+            state: from,
+            action,
+        });
+        return (
+            machine,
+            Applied {
+                from,
+                legal,
+                result: Err(error),
+            },
+        );
+    }
+    let (machine, result) = match machine.on_event(action, context).await {
+        Ok(next) => {
+            let to = next.state();
+            (next, Ok(to))
+        }
+        Err(TransitionError { machine, error }) => (machine, Err(error)),
+    };
+    (
+        machine,
+        Applied {
+            from,
+            legal,
+            result,
+        },
+    )
+}
+
+#[derive(Debug)]
+pub struct CarHandle<C = ElevatorContext>(mpsc::Sender<Message<C>>);
+
+impl<C> Clone for CarHandle<C> {
+    fn clone(&self) -> Self {
+        CarHandle(self.0.clone())
+    }
+}
+
+impl<C: CarContext> CarHandle<C> {
+    async fn request<R>(
+        &self,
+        message: impl FnOnce(oneshot::Sender<R>) -> Message<C>,
+    ) -> Result<R> {
+        let (reply, response) = oneshot::channel();
+        self.0
+            .send(message(reply))
+            .await
+            .map_err(|_| Error::CarStopped)?;
+        response.await.map_err(|_| Error::CarStopped)
+    }
+
+    /// Runs `action` through the state machine.
+    pub async fn apply(&self, action: Action, strict: bool) -> Result<Applied> {
+        self.request(|reply| Message::Apply {
+            action,
+            strict,
+            reply,
+        })
+        .await
+    }
+
+    pub async fn snapshot(&self) -> Result<Snapshot<C>> {
+        self.request(Message::Snapshot).await
+    }
+
+    /// Runs `f` on the context, with the current state, between two messages.
+    pub async fn update<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut C, State) -> R + Send + 'static,
+    ) -> Result<R> {
+        self.request(|reply| {
+            Message::Update(Box::new(move |context, state| {
+                let _ = reply.send(f(context, state));
+            }))
+        })
+        .await
+    }
+}
@@ -0,0 +1,289 @@
+use crate::car::CarHandle;
+use crate::context::{Call, ElevatorContext};
+use crate::decision::QueueSnapshot;
+use crate::position::{Direction, Position};
+use crate::strategies::switchable::SwitchableStrategy;
+use crate::transition::State;
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often the running controller writes its checkpoint.
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Older checkpoints are only used for their pending calls: the car may have
+/// been reset or driven by someone else in the meantime.
+pub const MAX_CHECKPOINT_AGE: Duration = Duration::from_secs(30);
+
+/// What a restarted controller needs to pick up where the last one stopped:
+/// the car's state and position, the key mode, the active strategy and every
+/// pending call.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub saved_at: SystemTime,
+    pub state: State,
+    pub strategy: Option<String>,
+    pub context: ElevatorContext,
+}
+
+impl Checkpoint {
+    /// Whether the hardware can be trusted to still be where the checkpoint
+    /// left it: a car at rest on a floor stays there until told otherwise,
+    /// while one that was moving or working its door has moved on since.
+    pub fn is_warm(&self, now: SystemTime) -> bool {
+        let fresh = now
+            .duration_since(self.saved_at)
+            .is_ok_and(|age| age <= MAX_CHECKPOINT_AGE);
+        let at_rest = matches!(self.state, State::Idle | State::DoorOpened);
+        fresh && at_rest && matches!(self.context.position, Position::Stopped(_))
+    }
+
+    /// Every pending call with where it was made, lowest floor first. The
+    /// target counts even when the car stood at it: a car that is reset
+    /// leaves that floor before its door opened.
+    pub fn pending_calls(&self) -> BTreeSet<(u8, Call)> {
+        let mut calls = self.context.pending();
+        if let Some(target) = self.context.active_target
+            && !calls.iter().any(|&(f, _)| f == target)
+        {
+            calls.insert((target, Call::Car));
+        }
+        calls
+    }
+
+    /// One `name value...` line per field.
+    pub fn encode(&self) -> String {
+        let ctx = &self.context;
+        let saved = self
+            .saved_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let position = match ctx.position {
+            Position::Stopped(floor) => floor.to_string(),
+            Position::Departing { from, direction } => {
+                format!("departing {from} {}", name(direction))
+            }
+            Position::Approaching { floor, direction } => {
+                format!("approaching {floor} {}", name(direction))
+            }
+            Position::Levelling { floor, direction } => {
+                format!("levelling {floor} {}", name(direction))
+            }
+        };
+        let target = ctx.active_target.map_or("-".to_string(), |f| f.to_string());
+        let QueueSnapshot { up, down } = QueueSnapshot::of(ctx);
+
+        let mut out = String::new();
+        let _ = writeln!(out, "saved {saved}");
+        let _ = writeln!(out, "state {:?}", self.state);
+        if let Some(strategy) = &self.strategy {
+            let _ = writeln!(out, "strategy {strategy}");
+        }
+        let _ = writeln!(out, "position {position}");
+        let _ = writeln!(
+            out,
+            "direction {}",
+            if ctx.direction_up { "up" } else { "down" }
+        );
+        let _ = writeln!(out, "target {target}");
+        let _ = writeln!(out, "key {}", ctx.key);
+        let _ = writeln!(out, "up{}", join(&up));
+        let _ = writeln!(out, "down{}", join(&down));
+        let calls: String = (ctx.calls.iter())
+            .map(|&(floor, call)| format!(" {}{floor}", letter(call)))
+            .collect();
+        let _ = writeln!(out, "calls{calls}");
+        out
+    }
+
+    /// Parses a checkpoint for a building with floors `min_floor..=max_floor`,
+    /// rejecting anything outside it.
+    pub fn decode(text: &str, min_floor: u8, max_floor: u8) -> io::Result<Checkpoint> {
+        let mut ctx = ElevatorContext::new(min_floor, max_floor);
+        let mut saved_at = None;
+        let mut state = None;
+        let mut strategy = None;
+        let mut position = None;
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            match name {
+                "saved" => saved_at = Some(UNIX_EPOCH + Duration::from_secs(number(value)?)),
+                "state" => state = Some(parse_state(value)?),
+                "strategy" => strategy = Some(value.to_string()),
+                "position" => position = Some(parse_position(value, &ctx)?),
+                "direction" => {
+                    ctx.direction_up = match value {
+                        "up" => true,
+                        "down" => false,
+                        _ => return Err(invalid(format!("direction {value:?}"))),
+                    }
+                }
+                "target" => {
+                    ctx.active_target = match value {
+                        "-" => None,
+                        floor => Some(parse_floor(floor, &ctx)?),
+                    }
+                }
+                "key" => ctx.key = number(value)?,
+                "up" => {
+                    for floor in value.split_whitespace() {
+                        ctx.up_queue.push(Reverse(parse_floor(floor, &ctx)?));
+                    }
+                }
+                "down" => {
+                    for floor in value.split_whitespace() {
+                        ctx.down_queue.push(parse_floor(floor, &ctx)?);
+                    }
+                }
+                "calls" => {
+                    for call in value.split_whitespace() {
+                        ctx.calls.insert(parse_call(call, &ctx)?);
+                    }
+                }
+                other => return Err(invalid(format!("unknown field {other:?}"))),
+            }
+        }
+        ctx.position = position.ok_or_else(|| invalid("missing position".into()))?;
+        Ok(Checkpoint {
+            saved_at: saved_at.ok_or_else(|| invalid("missing saved".into()))?,
+            state: state.ok_or_else(|| invalid("missing state".into()))?,
+            strategy,
+            context: ctx,
+        })
+    }
+
+    /// Writes the checkpoint next to `path` and renames it into place, so a
+    /// crash mid-write leaves the previous checkpoint intact.
+    pub async fn save(&self, path: &Path) -> io::Result<()> {
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".tmp");
+        let partial = PathBuf::from(partial);
+        tokio::fs::write(&partial, self.encode()).await?;
+        tokio::fs::rename(&partial, path).await
+    }
+
+    /// The checkpoint at `path`, or `None` if there is none yet.
+    pub async fn load(path: &Path, min_floor: u8, max_floor: u8) -> io::Result<Option<Self>> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(text) => Self::decode(&text, min_floor, max_floor).map(Some),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Checkpoints the car to `path` every `CHECKPOINT_INTERVAL` until the car stops.
+pub fn spawn_saver(path: PathBuf, car: CarHandle, strategy: SwitchableStrategy) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECKPOINT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Ok(snapshot) = car.snapshot().await else {
+                return;
+            };
+            let checkpoint = Checkpoint {
+                saved_at: SystemTime::now(),
+                state: snapshot.state,
+                strategy: Some(strategy.active_name()),
+                context: snapshot.context,
+            };
+            if let Err(e) = checkpoint.save(&path).await {
+                eprintln!("Checkpoint to {} failed: {e}", path.display());
+            }
+        }
+    });
+}
+
+/// Each floor preceded by a space, so an empty queue leaves a bare name.
+fn join(floors: &[u8]) -> String {
+    floors.iter().map(|floor| format!(" {floor}")).collect()
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("bad checkpoint: {message}"))
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> io::Result<T> {
+    value
+        .parse()
+        .map_err(|_| invalid(format!("{value:?} is not a number")))
+}
+
+fn parse_floor(value: &str, ctx: &ElevatorContext) -> io::Result<u8> {
+    let floor = number(value)?;
+    if !ctx.in_range(floor) {
+        return Err(invalid(format!("floor {floor} outside the building")));
+    }
+    Ok(floor)
+}
+
+/// The Lifty event letter of the button that made the call.
+fn letter(call: Call) -> &'static str {
+    match call {
+        Call::Car => "P",
+        Call::HallUp => "U",
+        Call::HallDown => "D",
+    }
+}
+
+/// `P3`, `U3` or `D3`: a call at floor 3 from the panel, or going up or down.
+fn parse_call(value: &str, ctx: &ElevatorContext) -> io::Result<(u8, Call)> {
+    let (letter, floor) = value.split_at_checked(1).unwrap_or((value, ""));
+    let call = match letter {
+        "P" => Call::Car,
+        "U" => Call::HallUp,
+        "D" => Call::HallDown,
+        _ => return Err(invalid(format!("call {value:?}"))),
+    };
+    Ok((parse_floor(floor, ctx)?, call))
+}
+
+fn name(direction: Direction) -> &'static str {
+    if direction.is_up() { "up" } else { "down" }
+}
+
+/// `3` when stopped, otherwise the phase, its floor and the direction.
+fn parse_position(value: &str, ctx: &ElevatorContext) -> io::Result<Position> {
+    let fields: Vec<_> = value.split_whitespace().collect();
+    let direction = |value: &str| match value {
+        "up" => Ok(Direction::Up),
+        "down" => Ok(Direction::Down),
+        _ => Err(invalid(format!("direction {value:?}"))),
+    };
+    let position = match fields[..] {
+        [floor] => Position::Stopped(parse_floor(floor, ctx)?),
+        ["departing", from, dir] => Position::Departing {
+            from: parse_floor(from, ctx)?,
+            direction: direction(dir)?,
+        },
+        ["approaching", floor, dir] => Position::Approaching {
+            floor: parse_floor(floor, ctx)?,
+            direction: direction(dir)?,
+        },
+        ["levelling", floor, dir] => Position::Levelling {
+            floor: parse_floor(floor, ctx)?,
+            direction: direction(dir)?,
+        },
+        _ => return Err(invalid(format!("position {value:?}"))),
+    };
+    // A car moving off the end of the shaft has nowhere to be.
+    if !position
+        .next_floor()
+        .is_some_and(|floor| ctx.in_range(floor))
+    {
+        return Err(invalid(format!("position {value:?}")));
+    }
+    Ok(position)
+}
+
+fn parse_state(value: &str) -> io::Result<State> {
+    State::ALL
+        .into_iter()
+        .find(|state| format!("{state:?}") == value)
+        .ok_or_else(|| invalid(format!("state {value:?}")))
+}
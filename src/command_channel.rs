@@ -0,0 +1,74 @@
+use crate::error::{Error, Result};
+use crate::types::cmd::CommandBatch;
+use tokio::sync::{mpsc, oneshot};
+
+/// Batches the state machine may queue before its sends start waiting.
+pub const COMMAND_CHANNEL_CAPACITY: usize = 16;
+
+/// A batch on its way to the transport, with the handle to report back on.
+#[derive(Debug)]
+pub struct PendingBatch {
+    pub batch: CommandBatch,
+    confirm: oneshot::Sender<Result<()>>,
+}
+
+impl PendingBatch {
+    /// Reports whether the batch was written. The sender may have given up
+    /// waiting, in which case nobody is told.
+    pub fn confirm(self, result: Result<()>) {
+        let _ = self.confirm.send(result);
+    }
+}
+
+/// Resolves once the transport has written (or failed to write) a batch.
+#[derive(Debug)]
+pub struct Delivery(oneshot::Receiver<Result<()>>);
+
+impl Delivery {
+    pub async fn sent(self) -> Result<()> {
+        self.0.await.map_err(|_| Error::CommandChannelClosed)?
+    }
+}
+
+/// The state machine's end of the bounded command channel.
+#[derive(Debug, Clone)]
+pub struct CommandSender(mpsc::Sender<PendingBatch>);
+
+pub type CommandReceiver = mpsc::Receiver<PendingBatch>;
+
+pub fn channel(capacity: usize) -> (CommandSender, CommandReceiver) {
+    let (tx, rx) = mpsc::channel(capacity);
+    (CommandSender(tx), rx)
+}
+
+impl CommandSender {
+    /// Queues `batch`, waiting for room in the channel, without waiting for
+    /// it to be written.
+    pub async fn enqueue(&self, batch: CommandBatch) -> Result<Delivery> {
+        let (confirm, delivery) = oneshot::channel();
+        self.0
+            .send(PendingBatch { batch, confirm })
+            .await
+            .map_err(|_| Error::CommandChannelClosed)?;
+        Ok(Delivery(delivery))
+    }
+
+    /// Queues `batch` and waits until the transport has written it.
+    pub async fn send(&self, batch: CommandBatch) -> Result<()> {
+        self.enqueue(batch).await?.sent().await
+    }
+}
+
+/// Confirms every batch as sent after handing it to `sink`, for simulators
+/// and test doubles that stand in for the network.
+pub fn spawn_loopback(
+    mut rx: CommandReceiver,
+    mut sink: impl FnMut(CommandBatch) + Send + 'static,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(PendingBatch { batch, confirm }) = rx.recv().await {
+            sink(batch);
+            let _ = confirm.send(Ok(()));
+        }
+    })
+}
@@ -0,0 +1,96 @@
+use anyhow::bail;
+
+/// How the scheduler responds once an action or event has been classed as a fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FaultPolicy {
+    /// Log and count the fault, keep serving calls.
+    #[default]
+    Alert,
+    /// Emergency stop and hold until an operator resets the controller.
+    StopSafely,
+    /// Reset the hardware and the controller state.
+    Reset,
+}
+
+/// How a transport turns queued command batches into datagrams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushPolicy {
+    /// One datagram per command, for peers that expect a single command per packet.
+    EachCommand,
+    /// One datagram per batch, so a stop's commands arrive together and in order.
+    #[default]
+    EachBatch,
+    /// Like `EachBatch`, but also merge batches already queued behind it, up
+    /// to `max_bytes` per datagram.
+    Coalesce { max_bytes: usize },
+}
+
+impl FlushPolicy {
+    /// Lifty reads at most 2000 bytes per datagram.
+    pub const LIFTY_DATAGRAM_BYTES: usize = 2000;
+
+    /// Reads `ELEVATOR_FLUSH` (`command`, `batch` or `coalesce`), defaulting when unset.
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(match std::env::var("ELEVATOR_FLUSH").as_deref() {
+            Ok("command") => FlushPolicy::EachCommand,
+            Ok("batch") | Err(_) => FlushPolicy::EachBatch,
+            Ok("coalesce") => FlushPolicy::Coalesce {
+                max_bytes: Self::LIFTY_DATAGRAM_BYTES,
+            },
+            Ok(other) => bail!("invalid ELEVATOR_FLUSH value {other:?}"),
+        })
+    }
+}
+
+/// How the controller finds out where the car is when it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Startup {
+    /// Send `R`, which puts Lifty back on the lowest floor.
+    #[default]
+    Reset,
+    /// Recall the car in this direction and take the first floor it
+    /// approaches, for hardware whose position cannot be reset.
+    Home { up: bool },
+}
+
+impl Startup {
+    /// Reads `ELEVATOR_HOMING` (`up` or `down`), resetting when unset.
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(match std::env::var("ELEVATOR_HOMING").as_deref() {
+            Err(_) => Startup::Reset,
+            Ok("up") => Startup::Home { up: true },
+            Ok("down") => Startup::Home { up: false },
+            Ok(other) => bail!("invalid ELEVATOR_HOMING value {other:?}"),
+        })
+    }
+}
+
+/// Per-deployment controller settings.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ControllerConfig {
+    /// Treat ignored actions and unexpected hardware events as faults.
+    pub strict: bool,
+    pub fault_policy: FaultPolicy,
+}
+
+impl ControllerConfig {
+    /// Reads `ELEVATOR_STRICT` (`1`/`true`) and `ELEVATOR_FAULT_POLICY`
+    /// (`alert`, `stop` or `reset`), defaulting when unset.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let strict = match std::env::var("ELEVATOR_STRICT").as_deref() {
+            Ok("1") | Ok("true") => true,
+            Ok("0") | Ok("false") | Err(_) => false,
+            Ok(other) => bail!("invalid ELEVATOR_STRICT value {other:?}"),
+        };
+        let fault_policy = match std::env::var("ELEVATOR_FAULT_POLICY").as_deref() {
+            Ok("alert") | Err(_) => FaultPolicy::Alert,
+            Ok("stop") => FaultPolicy::StopSafely,
+            Ok("reset") => FaultPolicy::Reset,
+            Ok(other) => bail!("invalid ELEVATOR_FAULT_POLICY value {other:?}"),
+        };
+        Ok(Self {
+            strict,
+            fault_policy,
+        })
+    }
+}
@@ -0,0 +1,183 @@
+use crate::car::CarHandle;
+use crate::context::{ElevatorContext, Location};
+use crate::decision::DecisionLog;
+use crate::transition::State;
+use crate::types::event::Event;
+use std::cmp::Reverse;
+use std::ops::{Range, RangeBounds, RangeInclusive};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+const HELP: &str = "\
+commands:
+  call N      - press panel button for floor N
+  up N        - press up button on floor N
+  down N      - press down button on floor N
+  cancel N    - cancel pending calls for floor N
+  key N       - switch key mode to N
+  load N      - report the car's load as N percent of capacity
+  stop        - emergency stop, hold until reset
+  reset       - reset hardware and controller
+  strategy S  - switch scheduling strategy to S
+  status      - print the status line
+  why [N]     - explain the last N target choices (default 5)
+  help        - print this help";
+
+/// Decisions `why` explains when not given a count.
+const DEFAULT_WHY: usize = 5;
+
+/// Operator REPL: turns typed lines into events for the service stack and
+/// keeps a live status line of the car, in the spirit of Lifty's own display.
+pub struct Console {
+    floors: RangeInclusive<u8>,
+    keys: Range<u8>,
+    car: CarHandle,
+    decisions: DecisionLog,
+    last: String,
+}
+
+impl Console {
+    pub fn new(
+        floors: RangeInclusive<u8>,
+        keys: Range<u8>,
+        car: CarHandle,
+        decisions: DecisionLog,
+    ) -> Self {
+        println!("{HELP}");
+        Self {
+            floors,
+            keys,
+            car,
+            decisions,
+            last: String::new(),
+        }
+    }
+
+    /// Forwards stdin lines until stdin closes.
+    pub fn spawn_reader() -> UnboundedReceiver<String> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(tokio::io::stdin()).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+
+    /// Parses an operator line, returning the event to inject, if any. A bad
+    /// line is reported and injects nothing.
+    pub fn handle_line(&mut self, line: &str) -> Option<Event> {
+        self.parse(line).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            None
+        })
+    }
+
+    /// The event an operator line injects, if any, or why the line is bad.
+    /// Commands that only print, like `why`, inject nothing.
+    pub fn parse(&mut self, line: &str) -> Result<Option<Event>, String> {
+        let mut words = line.split_whitespace();
+        let Some(cmd) = words.next() else {
+            return Ok(None);
+        };
+        let arg = words.next();
+        let event = match cmd.to_lowercase().as_str() {
+            "call" => Event::PanelButtonPressed(self.floor(arg)?),
+            "up" => Event::ElevatorUp(self.floor(arg)?),
+            "down" => Event::ElevatorDown(self.floor(arg)?),
+            "cancel" => Event::CallCancelled(self.floor(arg)?),
+            "key" => Event::KeySwitched(in_range(arg, &self.keys, "key")?),
+            "load" => Event::LoadWeighed(in_range(arg, &(0..=u8::MAX), "load")?),
+            "stop" => Event::EmergencyStop,
+            "reset" => Event::Reset,
+            "strategy" => Event::StrategySwitched(arg.ok_or("usage: strategy NAME")?.to_string()),
+            "status" => {
+                self.last.clear();
+                return Ok(None);
+            }
+            "why" => {
+                let n = match arg {
+                    Some(arg) => arg
+                        .parse()
+                        .map_err(|_| format!("{arg:?} is not a valid count"))?,
+                    None => DEFAULT_WHY,
+                };
+                let decisions = self.decisions.last(n);
+                if decisions.is_empty() {
+                    println!("no decisions yet");
+                }
+                for decision in decisions {
+                    println!("{decision}");
+                }
+                return Ok(None);
+            }
+            "help" => {
+                println!("{HELP}");
+                return Ok(None);
+            }
+            other => return Err(format!("unknown command {other:?}, try help")),
+        };
+        Ok(Some(event))
+    }
+
+    fn floor(&self, arg: Option<&str>) -> Result<u8, String> {
+        in_range(arg, &self.floors, "floor")
+    }
+
+    /// Prints the status line if it changed since the last refresh.
+    pub async fn refresh(&mut self) {
+        let Ok(snapshot) = self.car.snapshot().await else {
+            return;
+        };
+        let line = status_line(&snapshot.context, snapshot.state);
+        if line != self.last {
+            println!("{line}");
+            self.last = line;
+        }
+    }
+}
+
+fn in_range(arg: Option<&str>, range: &impl RangeBounds<u8>, what: &str) -> Result<u8, String> {
+    let arg = arg.ok_or(format!("missing {what}"))?;
+    let n = arg
+        .parse()
+        .map_err(|_| format!("{arg:?} is not a valid {what}"))?;
+    if !range.contains(&n) {
+        return Err(format!("{what} {n} out of range"));
+    }
+    Ok(n)
+}
+
+pub fn status_line(ctx: &ElevatorContext, state: State) -> String {
+    let floor = match ctx.location() {
+        _ if state == State::Homing => "?".to_string(),
+        Location::AtFloor(f) => format!("{f}"),
+        Location::BetweenFloors(l, h) => format!("{l}-{h}"),
+    };
+    let status = format!("{state:?}");
+    let door = match state {
+        State::DoorOpening => "OPENING",
+        State::DoorOpened => "OPEN",
+        State::DoorClosing => "CLOSING",
+        _ => "CLOSED",
+    };
+    let pending =
+        ctx.active_target.is_some() || !ctx.up_queue.is_empty() || !ctx.down_queue.is_empty();
+    let indicator = match (pending, ctx.direction_up) {
+        (false, _) => "--",
+        (true, true) => "^^",
+        (true, false) => "vv",
+    };
+    let mut up: Vec<_> = ctx.up_queue.iter().map(|&Reverse(f)| f).collect();
+    up.sort();
+    let mut down: Vec<_> = ctx.down_queue.iter().copied().collect();
+    down.sort_by(|a, b| b.cmp(a));
+    let target = ctx.active_target.map_or("-".to_string(), |f| f.to_string());
+    format!(
+        "[ FLOOR {floor} | {status:14} {indicator} | DOOR {door:7} | TARGET {target} | U:{up:?} | D:{down:?} | K{} ]",
+        ctx.key
+    )
+}
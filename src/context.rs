@@ -1,7 +1,12 @@
+use crate::decision::Reason;
+use crate::position::{Direction, Position};
 use std::cmp::{Ordering, Reverse};
-use std::collections::BinaryHeap;
+use std::collections::{BTreeSet, BinaryHeap};
+use std::fmt::Debug;
+use std::ops::RangeInclusive;
 
-#[derive(Debug, Clone, PartialEq)]
+/// Where the car is, to the floor: the coarse view of a `Position`.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Location {
     AtFloor(u8),
     BetweenFloors(u8, u8),
@@ -13,86 +18,579 @@ impl Default for Location {
     }
 }
 
+impl Location {
+    /// Height in half floors, with the lower floor as a tie-break for spans
+    /// that are not between neighbouring floors.
+    fn key(&self) -> (u16, u8) {
+        match *self {
+            Location::AtFloor(f) => (2 * f as u16, f),
+            Location::BetweenFloors(l, h) => (l as u16 + h as u16, l),
+        }
+    }
+}
+
+impl Ord for Location {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key()).then_with(|| {
+            matches!(self, Location::AtFloor(_)).cmp(&matches!(other, Location::AtFloor(_)))
+        })
+    }
+}
+
 impl PartialOrd for Location {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        use Location::*;
+        Some(self.cmp(other))
+    }
+}
 
-        fn midpoint(from: u8, to: u8) -> f32 {
-            (from as f32 + to as f32) / 2.0
-        }
+/// What the state machine needs from the building it drives: where the car
+/// is and the calls it still has to serve. `ElevatorContext` is the stock
+/// implementation; a building with extra sensors can wrap or replace it.
+pub trait CarContext: Debug + Send + Sync + 'static {
+    fn position(&self) -> Position;
 
-        let lhs = match self {
-            AtFloor(f) => *f as f32,
-            BetweenFloors(f1, f2) => midpoint(*f1, *f2),
-        };
+    fn location(&self) -> Location {
+        self.position().location()
+    }
 
-        let rhs = match other {
-            AtFloor(f) => *f as f32,
-            BetweenFloors(f1, f2) => midpoint(*f1, *f2),
-        };
+    /// The floors the car may stop at, lowest first.
+    fn floors(&self) -> RangeInclusive<u8>;
+
+    /// The motor was told to move: the car leaves the floor it stands at.
+    fn depart(&mut self, up: bool);
+
+    /// Lifty reported approaching `floor` (`An`).
+    fn approach(&mut self, floor: u8);
+
+    /// The car was told to stop (`S`) and levels into the next floor it can
+    /// still reach.
+    fn level(&mut self);
+
+    /// Lifty reported the car at rest at `floor`, stopped or working its door.
+    fn stopped_at(&mut self, floor: u8);
+
+    fn set_direction(&mut self, up: bool);
+
+    fn enqueue_request(&mut self, floor: u8);
+
+    fn cancel_request(&mut self, floor: u8) -> bool;
+
+    fn clear_requests(&mut self);
+
+    fn next_target(&mut self) -> Option<u8>;
 
-        lhs.partial_cmp(&rhs)
+    /// Mirrors a hardware reset (`R`).
+    fn reset(&mut self);
+
+    /// Whether the car carries more than it may move with. Contexts without
+    /// a load sensor never are.
+    fn overloaded(&self) -> bool {
+        false
+    }
+
+    /// Calls dropped since the last time their lamps were cleared. Contexts
+    /// that do not track lamps have none.
+    fn lamps_to_clear(&self) -> Vec<(u8, Call)> {
+        Vec::new()
     }
+
+    /// Forgets the lamps to clear, once they were.
+    fn take_lamps_to_clear(&mut self) -> Vec<(u8, Call)> {
+        Vec::new()
+    }
+
+    /// The calls a stop at `floor` satisfies. Contexts without a call
+    /// registry have none.
+    fn served_at(&self, _floor: u8) -> Vec<(u8, Call)> {
+        Vec::new()
+    }
+
+    /// The door opens at `floor`: the calls it satisfies are dropped.
+    fn serve(&mut self, _floor: u8) {}
 }
 
-#[derive(Debug, Default)]
+/// Load, in percent of capacity, above which the car stops for hall calls
+/// only when nothing else is left ahead.
+pub const NEAR_FULL_LOAD: u8 = 80;
+
+/// Load, in percent of capacity, above which the door stays open.
+pub const FULL_LOAD: u8 = 100;
+
+/// Where a call was made: the car's panel, or a hall button for a direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Call {
+    Car,
+    HallUp,
+    HallDown,
+}
+
+impl Call {
+    pub fn hall(up: bool) -> Self {
+        if up { Call::HallUp } else { Call::HallDown }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct ElevatorContext {
-    pub current_location: Location,
+    pub position: Position,
     pub direction_up: bool,
     pub up_queue: BinaryHeap<Reverse<u8>>,
     pub down_queue: BinaryHeap<u8>,
     pub active_target: Option<u8>,
     pub min_floor: u8,
     pub max_floor: u8,
+    pub key: u8,
+    /// The load sensor's last reading, in percent of rated capacity.
+    pub load: u8,
+    /// Every call waiting, by floor and where it was made. The queues plan
+    /// the stops; this tells which calls a stop satisfies.
+    pub calls: BTreeSet<(u8, Call)>,
+    /// Calls dropped whose buttons are still lit.
+    pub lamps_to_clear: Vec<(u8, Call)>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ScanStrategy {}
 
 impl ElevatorContext {
-    pub fn transit_floor(&mut self) {
-        self.current_location = match (&self.current_location, self.direction_up) {
-            (&Location::AtFloor(f), true) => Location::BetweenFloors(f, f + 1),
-            (&Location::AtFloor(f), false) => Location::BetweenFloors(f - 1, f),
-            (&Location::BetweenFloors(_l, h), true) => Location::AtFloor(h),
-            (&Location::BetweenFloors(l, _h), false) => Location::AtFloor(l),
+    pub fn new(min_floor: u8, max_floor: u8) -> Self {
+        ElevatorContext {
+            position: Position::Stopped(min_floor),
+            direction_up: true,
+            min_floor,
+            max_floor,
+            ..Default::default()
+        }
+    }
+
+    pub fn in_range(&self, floor: u8) -> bool {
+        (self.min_floor..=self.max_floor).contains(&floor)
+    }
+
+    pub fn location(&self) -> Location {
+        self.position.location()
+    }
+
+    pub fn depart(&mut self, up: bool) {
+        let direction = Direction::from_up(up);
+        let Position::Stopped(from) = self.position else {
+            eprintln!("departing while {:?}, ignored", self.position);
+            return;
         };
+        if !direction.step(from).is_some_and(|next| self.in_range(next)) {
+            eprintln!("can't leave floor {from} in this direction, staying put");
+            return;
+        }
+        self.position = Position::Departing { from, direction };
+    }
 
-        if let Location::AtFloor(f) = &self.current_location {
-            if f == &self.min_floor {
-                self.direction_up = true;
+    /// Follows the hardware even when it disagrees with the model, which
+    /// then had drifted.
+    pub fn approach(&mut self, floor: u8) {
+        if !self.in_range(floor) {
+            eprintln!("approaching floor {floor} outside the building, ignored");
+            return;
+        }
+        // Only a homing car moves without having departed.
+        let direction = self
+            .position
+            .direction()
+            .unwrap_or(Direction::from_up(self.direction_up));
+        let from = match direction {
+            Direction::Up => floor.checked_sub(1),
+            Direction::Down => floor.checked_add(1),
+        };
+        if !from.is_some_and(|from| self.in_range(from)) {
+            eprintln!("approaching floor {floor} against the direction of travel, ignored");
+            return;
+        }
+        let expected = match self.position {
+            Position::Stopped(_) => None,
+            Position::Departing { .. } => self.position.next_floor(),
+            Position::Approaching { floor, direction }
+            | Position::Levelling { floor, direction } => direction.step(floor),
+        };
+        if expected.is_some_and(|expected| expected != floor) {
+            eprintln!("expected to approach floor {expected:?}, but approaching {floor}");
+        }
+        self.position = Position::Approaching { floor, direction };
+    }
+
+    pub fn level(&mut self) {
+        self.position = match self.position {
+            Position::Departing { direction, .. } | Position::Approaching { direction, .. } => {
+                match self.position.next_floor() {
+                    Some(floor) => Position::Levelling { floor, direction },
+                    None => return,
+                }
             }
-            if f == &self.max_floor {
-                self.direction_up = false;
+            Position::Levelling { .. } => return,
+            Position::Stopped(floor) => {
+                eprintln!("stopping while stopped at floor {floor}, ignored");
+                return;
             }
+        };
+    }
+
+    pub fn stopped_at(&mut self, floor: u8) {
+        if !self.in_range(floor) {
+            eprintln!("stopped at floor {floor} outside the building, ignored");
+            return;
+        }
+        if let Position::Levelling {
+            floor: expected, ..
+        } = self.position
+            && expected != floor
+        {
+            eprintln!("expected to stop at floor {expected}, but stopped at {floor}");
+        }
+        self.position = Position::Stopped(floor);
+        if floor == self.min_floor {
+            self.direction_up = true;
+        }
+        if floor == self.max_floor {
+            self.direction_up = false;
         }
     }
 
-    pub fn approach_floor(&mut self, floor: u8) {
-        if self.direction_up {
-            self.current_location = Location::BetweenFloors(floor - 1, floor);
-        } else {
-            self.current_location = Location::BetweenFloors(floor, floor + 1);
+    /// The active target only counts while the car is still on its way there;
+    /// a call for the floor the car stands at must cycle the door again. A
+    /// hall call only counts as pending for a stop in its own direction.
+    fn is_pending(&self, floor: u8, call: Call) -> bool {
+        let heading = |up: bool| call == Call::Car || call == Call::hall(up);
+        (self.active_target == Some(floor)
+            && self.location() != Location::AtFloor(floor)
+            && heading(self.direction_up))
+            || (heading(true) && self.up_queue.iter().any(|&Reverse(f)| f == floor))
+            || (heading(false) && self.down_queue.iter().any(|&f| f == floor))
+    }
+
+    /// Whether a call made now for `floor` can still be served without
+    /// turning around. Lifty only honours `S` until the tick after the
+    /// approach, so once an approach was handled any later event is too late
+    /// to stop at that floor.
+    pub fn can_stop_at(&self, floor: u8) -> bool {
+        match self.position {
+            Position::Approaching { floor: passing, .. } if passing == floor => false,
+            position => position.can_stop_at(floor),
         }
     }
 
+    /// Queues a car call for `floor`, see `enqueue_call`.
     pub fn enqueue_request(&mut self, floor: u8) {
-        let request_location = Location::AtFloor(floor);
+        self.enqueue_call(floor, Call::Car);
+    }
+
+    /// Queues a call for `floor`. A car call on the current floor is queued
+    /// in the direction of travel, so the car serves it by opening its door;
+    /// hall calls wait for the sweep in their direction. A call for the next
+    /// floor that is still early enough becomes the target, so the car
+    /// brakes on the approach; one that is too late waits for the car to
+    /// come back.
+    pub fn enqueue_call(&mut self, floor: u8, call: Call) {
+        if !self.in_range(floor) {
+            eprintln!("Request for floor {floor} outside the building, ignored.");
+            return;
+        }
+        self.calls.insert((floor, call));
+        if self.is_pending(floor, call) {
+            return;
+        }
+        let direction = self.position.direction();
+        if let Position::Departing { .. } = self.position
+            && self.position.next_floor() == Some(floor)
+            && (call == Call::Car || !self.near_full())
+            && direction.is_some_and(|d| call == Call::Car || call == Call::hall(d.is_up()))
+            && let Some(target) = self.active_target
+        {
+            self.active_target = Some(floor);
+            println!("Stopping at floor {floor} on the way to {target}");
+            self.requeue(target);
+            return;
+        }
+        match call {
+            Call::Car => self.queue(floor),
+            Call::HallUp => self.up_queue.push(Reverse(floor)),
+            Call::HallDown => self.down_queue.push(floor),
+        }
+    }
 
-        if request_location > self.current_location {
-            if !self.up_queue.iter().any(|&Reverse(f)| f == floor) {
-                self.up_queue.push(Reverse(floor));
+    /// Puts `floor` back in the queues after it was taken as the target:
+    /// each call there by where it was made, a floor without one as a car
+    /// call.
+    pub fn requeue(&mut self, floor: u8) {
+        let calls: Vec<Call> = (self.calls.iter())
+            .filter(|&&(f, _)| f == floor)
+            .map(|&(_, call)| call)
+            .collect();
+        if calls.is_empty() {
+            self.queue(floor);
+        }
+        for call in calls {
+            self.enqueue_call(floor, call);
+        }
+    }
+
+    /// Gives up `floor` as the target when the move toward it did not run.
+    /// A newer event may have replaced the target already; that one stays.
+    pub fn release_target(&mut self, floor: u8) {
+        if self.active_target == Some(floor) {
+            self.active_target = None;
+            self.requeue(floor);
+        }
+    }
+
+    /// Registers a call without queuing it, for a homing car whose floor is
+    /// not known yet; `queue_held_calls` queues it once it is.
+    pub fn hold_call(&mut self, floor: u8, call: Call) {
+        if !self.in_range(floor) {
+            eprintln!("Request for floor {floor} outside the building, ignored.");
+            return;
+        }
+        self.calls.insert((floor, call));
+    }
+
+    /// Queues every registered call, e.g. those held while homing.
+    pub fn queue_held_calls(&mut self) {
+        let floors: BTreeSet<u8> = self.calls.iter().map(|&(f, _)| f).collect();
+        for floor in floors {
+            self.requeue(floor);
+        }
+    }
+
+    fn queue(&mut self, floor: u8) {
+        let up = match self.position {
+            Position::Stopped(at) | Position::Levelling { floor: at, .. } => {
+                floor > at || (floor == at && self.direction_up)
             }
-        } else if request_location < self.current_location {
-            if !self.down_queue.iter().any(|&f| f == floor) {
-                self.down_queue.push(floor);
+            // A floor the car can no longer stop at is served on the way back.
+            Position::Departing { direction, .. } | Position::Approaching { direction, .. } => {
+                direction.is_up() == self.can_stop_at(floor)
             }
+        };
+        if up {
+            self.up_queue.push(Reverse(floor));
         } else {
-            println!("Request is on current floor, no queueing needed.");
+            self.down_queue.push(floor);
         }
     }
 
+    pub fn near_full(&self) -> bool {
+        self.load >= NEAR_FULL_LOAD
+    }
+
+    /// A near-full car has no room for whoever waits in the hall, so a
+    /// floor with only hall calls is no reason to stop.
+    fn hall_calls_only(&self, floor: u8) -> bool {
+        self.near_full() && !self.calls.contains(&(floor, Call::Car))
+    }
+
+    /// Makes an approached `floor` the target if a call in the direction of
+    /// travel waits there, putting the previous target back in its queue.
+    pub fn stop_on_the_way(&mut self, floor: u8) -> bool {
+        let Some(target) = self.active_target else {
+            return false;
+        };
+        if self.hall_calls_only(floor) {
+            return false;
+        }
+        let taken = match self.position.direction() {
+            _ if target == floor => false,
+            Some(Direction::Up) => {
+                let before = self.up_queue.len();
+                self.up_queue.retain(|&Reverse(f)| f != floor);
+                before != self.up_queue.len()
+            }
+            Some(Direction::Down) => {
+                let before = self.down_queue.len();
+                self.down_queue.retain(|&f| f != floor);
+                before != self.down_queue.len()
+            }
+            None => false,
+        };
+        if taken {
+            self.active_target = Some(floor);
+            self.requeue(target);
+        }
+        taken
+    }
+
+    /// Passes an approached target that only has hall calls while the car is
+    /// near full, heading for the nearest car call ahead instead. The hall
+    /// calls wait in their own direction's queue. Without a car call ahead the car
+    /// stops anyway.
+    pub fn bypass(&mut self, floor: u8) -> bool {
+        if self.active_target != Some(floor) || !self.hall_calls_only(floor) {
+            return false;
+        }
+        let Some(next) = (self.calls.iter())
+            .filter(|&&(f, call)| call == Call::Car && f != floor && self.can_stop_at(f))
+            .map(|&(f, _)| f)
+            .min_by_key(|f| f.abs_diff(floor))
+        else {
+            return false;
+        };
+        self.up_queue.retain(|&Reverse(f)| f != next);
+        self.down_queue.retain(|&f| f != next);
+        self.active_target = Some(next);
+        self.requeue(floor);
+        true
+    }
+
+    /// The way the car leaves `floor`: on in its direction if anything is
+    /// queued ahead, otherwise back if anything waits behind, otherwise none.
+    fn leaving(&self, floor: u8) -> Option<bool> {
+        let ahead = |up: bool| {
+            (self.up_queue.iter().map(|&Reverse(f)| f))
+                .chain(self.down_queue.iter().copied())
+                .any(|f| if up { f > floor } else { f < floor })
+        };
+        [self.direction_up, !self.direction_up]
+            .into_iter()
+            .find(|&up| ahead(up))
+    }
+
+    /// The calls a stop at `floor` satisfies: car calls always do; hall calls
+    /// do unless the car leaves against them.
+    pub fn served_at(&self, floor: u8) -> Vec<(u8, Call)> {
+        let kept = self.leaving(floor).map(|up| (floor, Call::hall(!up)));
+        (self.calls.iter())
+            .filter(|&&(f, call)| f == floor && Some((f, call)) != kept)
+            .copied()
+            .collect()
+    }
+
+    /// The car opens its door at `floor`: every call there that the stop
+    /// satisfies leaves all queues and has its lamp cleared. With nothing
+    /// left ahead the car turns around here if anything waits the other way,
+    /// and otherwise stays, which serves both.
+    pub fn serve(&mut self, floor: u8) {
+        let leaving = self.leaving(floor);
+        let served = self.served_at(floor);
+        self.calls.retain(|call| !served.contains(call));
+        self.lamps_to_clear.extend(served);
+        self.up_queue.retain(|&Reverse(f)| f != floor);
+        self.down_queue.retain(|&f| f != floor);
+        if let Some(up) = leaving {
+            self.direction_up = up;
+        }
+        let kept = leaving.map(|up| (floor, Call::hall(!up)));
+        if let Some((floor, call)) = kept
+            && self.calls.contains(&(floor, call))
+        {
+            self.enqueue_call(floor, call);
+        }
+    }
+
+    /// Drops every call for `floor` and has their lamps cleared. A moving car
+    /// whose target this was heads elsewhere; one already levelling into it
+    /// or standing there stops anyway.
+    pub fn cancel_request(&mut self, floor: u8) -> bool {
+        let before = self.up_queue.len() + self.down_queue.len() + self.calls.len();
+        self.up_queue.retain(|&Reverse(f)| f != floor);
+        self.down_queue.retain(|&f| f != floor);
+        self.calls.retain(|&(f, _)| f != floor);
+        let mut cancelled =
+            before != self.up_queue.len() + self.down_queue.len() + self.calls.len();
+        if self.active_target == Some(floor) {
+            match self.position {
+                Position::Stopped(at) if at != floor => self.active_target = None,
+                Position::Departing { .. } | Position::Approaching { .. } => self.retarget(),
+                Position::Stopped(_) | Position::Levelling { .. } => {}
+            }
+            cancelled = true;
+        }
+        if cancelled {
+            self.lamps_to_clear
+                .extend([Call::Car, Call::HallUp, Call::HallDown].map(|call| (floor, call)));
+        }
+        cancelled
+    }
+
+    /// Replaces the target of a moving car: the nearest call ahead it can
+    /// still stop for, or else the next floor it can stop at at all.
+    fn retarget(&mut self) {
+        let Some(direction) = self.position.direction() else {
+            self.active_target = None;
+            return;
+        };
+        let ahead = match direction {
+            Direction::Up => (self.up_queue.iter().map(|&Reverse(f)| f))
+                .filter(|&f| self.can_stop_at(f))
+                .min(),
+            Direction::Down => (self.down_queue.iter().copied())
+                .filter(|&f| self.can_stop_at(f))
+                .max(),
+        };
+        let next = ahead.or_else(|| {
+            std::iter::successors(self.position.next_floor(), |&f| direction.step(f))
+                .take_while(|&f| self.in_range(f))
+                .find(|&f| self.can_stop_at(f))
+        });
+        if let Some(next) = next {
+            self.up_queue.retain(|&Reverse(f)| f != next);
+            self.down_queue.retain(|&f| f != next);
+        }
+        self.active_target = next;
+    }
+
+    /// Every call the car still owes, e.g. to queue again after a reset. A
+    /// floor queued or targeted without a registered call counts as a car
+    /// call; a target the car stands at is being served already.
+    pub fn pending(&self) -> BTreeSet<(u8, Call)> {
+        let target = (self.active_target).filter(|&f| self.location() != Location::AtFloor(f));
+        let mut pending = self.calls.clone();
+        for floor in (self.up_queue.iter().map(|&Reverse(f)| f))
+            .chain(self.down_queue.iter().copied())
+            .chain(target)
+        {
+            if !pending.iter().any(|&(f, _)| f == floor) {
+                pending.insert((floor, Call::Car));
+            }
+        }
+        pending
+    }
+
+    pub fn clear_requests(&mut self) {
+        self.up_queue.clear();
+        self.down_queue.clear();
+        self.calls.clear();
+    }
+
+    /// Mirrors a hardware reset (`R`): the car is back at the lowest floor with no calls.
+    pub fn reset(&mut self) {
+        self.clear_requests();
+        self.lamps_to_clear.clear();
+        self.active_target = None;
+        self.position = Position::Stopped(self.min_floor);
+        self.direction_up = true;
+    }
+
+    /// A near-full car heads for its riders' floors before hall calls.
     fn next_target_in_direction(&mut self) -> Option<u8> {
+        if self.near_full() {
+            let mut floors: Vec<u8> = if self.direction_up {
+                self.up_queue.iter().map(|&Reverse(f)| f).collect()
+            } else {
+                self.down_queue.iter().copied().collect()
+            };
+            floors.sort_by_key(|&f| {
+                if self.direction_up {
+                    f as i16
+                } else {
+                    -(f as i16)
+                }
+            });
+            if let Some(rider) = floors.into_iter().find(|&f| !self.hall_calls_only(f)) {
+                if self.direction_up {
+                    self.up_queue.retain(|&Reverse(f)| f != rider);
+                } else {
+                    self.down_queue.retain(|&f| f != rider);
+                }
+                self.active_target = Some(rider);
+                return Some(rider);
+            }
+        }
         let next_target = if self.direction_up {
             self.up_queue.pop().map(|Reverse(f)| f)
         } else {
@@ -103,10 +601,104 @@ impl ElevatorContext {
     }
 
     pub fn next_target(&mut self) -> Option<u8> {
-        if let Some(floor) = self.next_target_in_direction() {
-            return Some(floor);
+        self.choose_target().map(|(target, _)| target)
+    }
+
+    /// Picks the next target like `next_target`, along with why.
+    pub fn choose_target(&mut self) -> Option<(u8, Reason)> {
+        let (target, mut reason) = match self.next_target_in_direction() {
+            Some(floor) => (floor, Reason::SameDirectionNearest),
+            None => {
+                self.direction_up = !self.direction_up;
+                (self.next_target_in_direction()?, Reason::Reversed)
+            }
+        };
+        // A floor queued before the car passed it may now lie behind the car.
+        let target_location = Location::AtFloor(target);
+        let toward_up = match target_location.cmp(&self.location()) {
+            Ordering::Greater => Some(true),
+            Ordering::Less => Some(false),
+            Ordering::Equal => None,
+        };
+        match toward_up {
+            Some(up) => {
+                if up != self.direction_up {
+                    reason = Reason::Behind;
+                }
+                self.direction_up = up;
+            }
+            None => reason = Reason::CurrentFloor,
         }
-        self.direction_up = !self.direction_up;
-        self.next_target_in_direction()
+        Some((target, reason))
+    }
+}
+
+impl CarContext for ElevatorContext {
+    fn position(&self) -> Position {
+        self.position
+    }
+
+    fn floors(&self) -> RangeInclusive<u8> {
+        self.min_floor..=self.max_floor
+    }
+
+    fn depart(&mut self, up: bool) {
+        ElevatorContext::depart(self, up)
+    }
+
+    fn approach(&mut self, floor: u8) {
+        ElevatorContext::approach(self, floor)
+    }
+
+    fn level(&mut self) {
+        ElevatorContext::level(self)
+    }
+
+    fn stopped_at(&mut self, floor: u8) {
+        ElevatorContext::stopped_at(self, floor)
+    }
+
+    fn set_direction(&mut self, up: bool) {
+        self.direction_up = up;
+    }
+
+    fn overloaded(&self) -> bool {
+        self.load > FULL_LOAD
+    }
+
+    fn lamps_to_clear(&self) -> Vec<(u8, Call)> {
+        self.lamps_to_clear.clone()
+    }
+
+    fn take_lamps_to_clear(&mut self) -> Vec<(u8, Call)> {
+        std::mem::take(&mut self.lamps_to_clear)
+    }
+
+    fn served_at(&self, floor: u8) -> Vec<(u8, Call)> {
+        ElevatorContext::served_at(self, floor)
+    }
+
+    fn serve(&mut self, floor: u8) {
+        ElevatorContext::serve(self, floor)
+    }
+
+    fn enqueue_request(&mut self, floor: u8) {
+        ElevatorContext::enqueue_request(self, floor)
+    }
+
+    fn cancel_request(&mut self, floor: u8) -> bool {
+        ElevatorContext::cancel_request(self, floor)
+    }
+
+    fn clear_requests(&mut self) {
+        ElevatorContext::clear_requests(self)
+    }
+
+    fn next_target(&mut self) -> Option<u8> {
+        ElevatorContext::next_target(self)
+    }
+
+    fn reset(&mut self) {
+        ElevatorContext::reset(self)
     }
 }
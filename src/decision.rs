@@ -0,0 +1,129 @@
+use crate::context::{ElevatorContext, Location};
+use crate::transition::State;
+use crate::types::event::Event;
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::fmt::{self, Display};
+use std::sync::{Arc, Mutex};
+
+/// Decisions kept by default, oldest dropped first.
+pub const DECISION_LOG_CAPACITY: usize = 100;
+
+/// Why a strategy picked its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// The nearest call ahead in the direction of travel.
+    SameDirectionNearest,
+    /// Nothing was left ahead, so the car turned around.
+    Reversed,
+    /// The call was queued before the car passed its floor; the car turns back.
+    Behind,
+    /// The call is for the floor the car stands at.
+    CurrentFloor,
+    /// The car was approaching the call's floor on the way to another target.
+    OnTheWay,
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Reason::SameDirectionNearest => "same direction nearest",
+            Reason::Reversed => "no calls ahead, reversed",
+            Reason::Behind => "passed call, turning back",
+            Reason::CurrentFloor => "call at the current floor",
+            Reason::OnTheWay => "call on the way, stopping",
+        })
+    }
+}
+
+/// The pending calls, lowest floor first, as they were before a choice.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueueSnapshot {
+    pub up: Vec<u8>,
+    pub down: Vec<u8>,
+}
+
+impl QueueSnapshot {
+    pub fn of(ctx: &ElevatorContext) -> Self {
+        let mut up: Vec<_> = ctx.up_queue.iter().map(|&Reverse(f)| f).collect();
+        up.sort();
+        let mut down: Vec<_> = ctx.down_queue.iter().copied().collect();
+        down.sort();
+        QueueSnapshot { up, down }
+    }
+}
+
+/// One target choice and the queues it was chosen from.
+#[derive(Debug, Clone)]
+pub struct Decision {
+    pub event: Event,
+    pub state: State,
+    pub location: Location,
+    pub direction_up: bool,
+    pub chosen: u8,
+    pub reason: Reason,
+    pub queues: QueueSnapshot,
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "on {:?} in {:?} at {:?} going {}: chose {} ({}) from up {:?} down {:?}",
+            self.event,
+            self.state,
+            self.location,
+            if self.direction_up { "up" } else { "down" },
+            self.chosen,
+            self.reason,
+            self.queues.up,
+            self.queues.down,
+        )
+    }
+}
+
+/// The most recent decisions, shared by the scheduler that records them and
+/// whoever wants to know why the car went where it did.
+#[derive(Debug, Clone)]
+pub struct DecisionLog {
+    capacity: usize,
+    records: Arc<Mutex<VecDeque<Decision>>>,
+}
+
+impl Default for DecisionLog {
+    fn default() -> Self {
+        Self::new(DECISION_LOG_CAPACITY)
+    }
+}
+
+impl DecisionLog {
+    pub fn new(capacity: usize) -> Self {
+        DecisionLog {
+            capacity,
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// Keeps `decision`, dropping the oldest ones beyond the capacity. A log
+    /// with no capacity keeps nothing.
+    pub fn record(&self, decision: Decision) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records = self.records.lock().unwrap();
+        while records.len() >= self.capacity {
+            records.pop_front();
+        }
+        records.push_back(decision);
+    }
+
+    /// Up to `n` of the latest decisions, oldest first.
+    pub fn last(&self, n: usize) -> Vec<Decision> {
+        let records = self.records.lock().unwrap();
+        records
+            .iter()
+            .skip(records.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    }
+}
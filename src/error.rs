@@ -0,0 +1,72 @@
+use crate::transition::State;
+use crate::types::sched_events::Action;
+
+/// Errors raised across the service stack, grouped by how callers should react.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum Error {
+    #[error("invalid packet: {0}")]
+    InvalidPacket(String),
+    #[error("anomaly: {0}")]
+    Anomaly(Anomaly),
+    #[error("transport send failed: {0}")]
+    Transport(String),
+    #[error("gave up after {attempts} attempts: {last}")]
+    GaveUp { attempts: u32, last: Box<Error> },
+    #[error("command channel closed")]
+    CommandChannelClosed,
+    #[error("car task stopped")]
+    CarStopped,
+}
+
+/// Ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorClass {
+    /// Bad input from the outside world; log it and carry on.
+    Drop,
+    /// Transient failure; the same action may succeed if tried again.
+    Retry,
+    /// The controller did something wrong; abandon the current plan.
+    Fault,
+    /// The controller can no longer operate safely and must stop.
+    Escalate,
+}
+
+impl Error {
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Error::InvalidPacket(_) => ErrorClass::Drop,
+            Error::Transport(_) => ErrorClass::Retry,
+            // A transient failure that outlasted its retries is not transient.
+            Error::GaveUp { .. } => ErrorClass::Fault,
+            Error::CommandChannelClosed => ErrorClass::Escalate,
+            Error::Anomaly(_) => ErrorClass::Fault,
+            Error::CarStopped => ErrorClass::Escalate,
+        }
+    }
+}
+
+/// Behaviour the controller tolerates by default but treats as a fault in strict mode.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Anomaly {
+    #[error("illegal transition: {action:?} in state {state:?}")]
+    IllegalTransition { state: State, action: Action },
+    #[error("door opened on unexpected floor {0}")]
+    UnexpectedDoorOpened(u8),
+    #[error("door closed on unexpected floor {0}")]
+    UnexpectedDoorClosed(u8),
+    #[error("stopped on unexpected floor {0}")]
+    UnexpectedStop(u8),
+}
+
+impl Anomaly {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Anomaly::IllegalTransition { .. } => "IllegalTransition",
+            Anomaly::UnexpectedDoorOpened(_) => "UnexpectedDoorOpened",
+            Anomaly::UnexpectedDoorClosed(_) => "UnexpectedDoorClosed",
+            Anomaly::UnexpectedStop(_) => "UnexpectedStop",
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,6 +1,19 @@
+pub mod car;
+pub mod checkpoint;
+pub mod command_channel;
+pub mod config;
+pub mod console;
 pub mod context;
+pub mod decision;
+pub mod error;
+pub mod lifty;
+pub mod metrics;
+pub mod model_check;
+pub mod passengers;
+pub mod position;
 pub mod services;
 pub mod strategies;
 pub mod strategy;
 pub mod transition;
+pub mod transition_table;
 pub mod types;
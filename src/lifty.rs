@@ -0,0 +1,486 @@
+/*
+lifty.rs
+
+Author:  David Beazley (https://www.dabeaz.com)
+Source:  https://github.com/dabeaz/lifty
+
+Copyright (C) 2025
+All Rights Reserved
+
+This code may be freely copied, modified, and used for EDUCATIONAL
+PURPOSES ONLY provided that the above attribution, URLs, and copyright
+notice are preserved in all copies.
+-----------------------------------------------------------------------------
+
+Hi, I'm a Lifty, a hardware simulator for a basic 5-floor elevator
+system with a single elevator car.  I have the following hardware
+features:
+
+  - A motor that makes the car go up and down.
+  - A door that can open and close.
+  - A panel of 5 buttons inside the elevator car.
+  - Up request buttons on floors 1-4.
+  - Down request buttons on floors 2-5.
+  - A direction indicator light on each floor.
+  - A 3-position "key" switch that can enable optional modes.
+
+Residents of the building interact with me by pressing buttons.
+This is done by typing the following commands at the keyboard:
+
+  Pn - Press button for floor n in the elevator car
+  Un - Press up button on floor n
+  Dn - Press down button on floor n
+  Wn - Set the load in the car to n percent of capacity
+
+Sadly, I don't have any brains of my own to know what to do
+when a button is pressed.  However, I can interact with a
+separate control program via UDP sockets.
+
+     Resident -> [ Lifty ] <--------> [ Control ]
+           buttons             UDP
+
+I will send the following event messages to the controller:
+
+  Pn - Panel button for floor n was pressed
+  Un - Up button on floor n was pressed
+  Dn - Down button floor n was pressed
+  An - Approaching floor n (still in motion)
+  Sn - Stopped at floor n (safe to open door)
+  On - Door open on floor n (doors have fully opened)
+  Cn - Door closed on floor n (now safe to move)
+  Kn - Key switch changed to position n
+  Wn - Load sensor reads n percent of the car's capacity
+
+I understand the following commands from the controller
+
+  MU  - Start moving up
+  MD  - Start moving down
+  S   - Stop at the next floor (generates Sn event when stopped)
+  DO  - Open door (will generate On event when done)
+  DC  - Close the door (will generate Cn event when done)
+  CPn - Clear panel button n
+  CUn - Clear up button n
+  CDn - Clear down button n
+  IUn - Set indicator light on floor n to "up"
+  IDn - Set indicator light on floor n to "down"
+  CIn - Clear the indicator light on floor n
+  R   - Reset
+
+Although I don't have any brains, I am programmed with some
+some basic protection features and am prone to crashing
+if I'm given bad instructions.  If I crash, I'll enter a
+permanent crashed state that can only be reset by rebooting
+the control software and having it send a reset (R) command.
+
+Your challenge, should you choose to accept it--write a control
+program that runs the elevator algorithm and prove that (a) it works
+like an actual elevator and (b) it will never cause the elevator to
+crash.  Good luck!
+
+*/
+
+// The hardware model, shared by the `lify` simulator binary and the
+// `lify_check` model checker. The network runtime lives in `src/bin/lify.rs`.
+
+use crate::types::cmd::Command;
+use crate::types::event::Event;
+
+// The building
+pub const MIN_FLOOR: u8 = 1;
+pub const MAX_FLOOR: u8 = 5;
+
+// Internal timing
+pub const TICKS_PER_FLOOR: usize = 40;
+pub const TICKS_FOR_DOOR: usize = 20;
+pub const APPROACH_TICKS: usize = 10;
+pub const TICK_INTERVAL: u64 = 100;
+
+// Turn this on if you want Lifty to be super picky or
+// if you're looking for ways to deduct grading points.
+const PEDANTIC: bool = false;
+
+// Hoist motor status
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Motor {
+    Up,
+    Down,
+    Off,
+}
+
+// Door status
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Door {
+    Opening,
+    Open,
+    Closing,
+    Closed,
+}
+
+// Direction indicators
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Indicator {
+    Up,
+    Down,
+    Off,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Elevator {
+    pub floor: usize,
+    pub panel_buttons: [bool; 5], // Buttons in the car
+    pub up_buttons: [bool; 5],    // Up buttons in the building
+    pub down_buttons: [bool; 5],  // Down buttons in the building
+    pub indicator: Indicator,     // Indicator light status
+    pub indicator_floor: usize,
+    pub clock: usize,
+    pub motor: Motor,
+    pub door: Door,
+    pub stopping: bool,
+    pub crashed: bool,
+    pub crash_reason: Option<&'static str>,
+    pub key: usize,  // Key switch setting
+    pub load: usize, // Load sensor, percent of capacity
+}
+
+impl Default for Elevator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Elevator {
+    pub fn new() -> Elevator {
+        Elevator {
+            floor: 1,
+            panel_buttons: [false, false, false, false, false],
+            up_buttons: [false, false, false, false, false],
+            down_buttons: [false, false, false, false, false],
+            indicator: Indicator::Off,
+            indicator_floor: 1,
+            clock: 0,
+            motor: Motor::Off,
+            door: Door::Closed,
+            stopping: false,
+            crashed: false,
+            crash_reason: None,
+            key: 0,
+            load: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.floor = 1;
+        self.panel_buttons = [false, false, false, false, false];
+        self.up_buttons = [false, false, false, false, false];
+        self.down_buttons = [false, false, false, false, false];
+        self.indicator = Indicator::Off;
+        self.indicator_floor = 1;
+        self.clock = 0;
+        self.motor = Motor::Off;
+        self.door = Door::Closed;
+        self.stopping = false;
+        self.crashed = false;
+        self.crash_reason = None;
+    }
+
+    // The runtime reports the crash; the model only records it.
+    fn crash(&mut self, reason: &'static str) {
+        self.crashed = true;
+        self.crash_reason = Some(reason);
+    }
+
+    pub fn as_string(&self) -> String {
+        let mut ps = String::from("P:");
+        for (n, floor) in self.panel_buttons.iter().enumerate() {
+            if *floor {
+                ps.push(char::from_u32(49 + n as u32).unwrap());
+            } else {
+                ps.push('-');
+            }
+        }
+        let mut us = String::from("U:");
+        for (n, floor) in self.up_buttons.iter().enumerate() {
+            if *floor {
+                us.push(char::from_u32(49 + n as u32).unwrap());
+            } else {
+                us.push('-');
+            }
+        }
+        let mut ds = String::from("D:");
+        for (n, floor) in self.down_buttons.iter().enumerate() {
+            if *floor {
+                ds.push(char::from_u32(49 + n as u32).unwrap());
+            } else {
+                ds.push('-');
+            }
+        }
+        let indicator = if self.indicator_floor == self.floor {
+            match self.indicator {
+                Indicator::Up => "^^",
+                Indicator::Down => "vv",
+                Indicator::Off => "--",
+            }
+        } else {
+            "--"
+        };
+        let status = if self.crashed {
+            "CRASH"
+        } else if self.stopping && self.clock >= (TICKS_PER_FLOOR - APPROACH_TICKS) {
+            "STOPPING"
+        } else if self.motor == Motor::Up {
+            "UP"
+        } else if self.motor == Motor::Down {
+            "DOWN"
+        } else if self.door == Door::Opening {
+            "OPENING"
+        } else if self.door == Door::Open {
+            "OPEN"
+        } else if self.door == Door::Closing {
+            "CLOSING"
+        } else if self.door == Door::Closed {
+            "CLOSED"
+        } else {
+            panic!("Can't determine status")
+        };
+        let key = if self.key > 0 {
+            format!(" | K{}", self.key)
+        } else {
+            String::from(" ")
+        };
+        let load = if self.load > 0 {
+            format!(" | W{}", self.load)
+        } else {
+            String::new()
+        };
+        format!(
+            "[ FLOOR {} | {status:8} {indicator} | {ps} | {us} | {ds}{key}{load} ]",
+            self.floor
+        )
+    }
+
+    fn set_panel_button(&mut self, floor: usize) {
+        self.panel_buttons[floor - 1] = true;
+    }
+
+    fn clear_panel_button(&mut self, floor: usize) {
+        if PEDANTIC && !self.panel_buttons[floor - 1] {
+            self.crash("panel button not previously set");
+        } else {
+            self.panel_buttons[floor - 1] = false;
+        }
+    }
+
+    fn set_up_button(&mut self, floor: usize) {
+        self.up_buttons[floor - 1] = true;
+    }
+
+    fn clear_up_button(&mut self, floor: usize) {
+        if PEDANTIC && !self.up_buttons[floor - 1] {
+            self.crash("up button not previously set");
+        } else {
+            self.up_buttons[floor - 1] = false;
+        }
+    }
+
+    fn set_down_button(&mut self, floor: usize) {
+        self.down_buttons[floor - 1] = true;
+    }
+
+    fn clear_down_button(&mut self, floor: usize) {
+        if PEDANTIC && !self.down_buttons[floor - 1] {
+            self.crash("down button not previously set");
+        } else {
+            self.down_buttons[floor - 1] = false;
+        }
+    }
+
+    fn set_indicator(&mut self, floor: usize, status: Indicator) {
+        if self.indicator != Indicator::Off && status != Indicator::Off {
+            self.crash("direction indicator already illuminated");
+        } else if PEDANTIC && self.indicator == Indicator::Off && status == Indicator::Off {
+            self.crash("direction indicator already off");
+        } else {
+            self.indicator = status;
+            self.indicator_floor = floor;
+        }
+    }
+
+    fn set_motor(&mut self, status: Motor) {
+        if self.door != Door::Closed {
+            self.crash("motor command received while doors open");
+            return;
+        }
+        if self.motor == Motor::Up && status == Motor::Down {
+            self.crash("violent direction switch (up->down)");
+            return;
+        }
+        if self.motor == Motor::Down && status == Motor::Up {
+            self.crash("violent direction switch (down->up)");
+            return;
+        }
+        if self.motor != status {
+            self.motor = status;
+            self.clock = 0;
+        } else if status == Motor::Up {
+            self.crash("already moving up");
+        } else if status == Motor::Down {
+            self.crash("already moving down");
+        }
+    }
+
+    fn set_door(&mut self, status: Door) {
+        if self.motor != Motor::Off {
+            self.crash("door command received while moving");
+            return;
+        }
+        if self.door == Door::Closing && status != Door::Closed {
+            self.crash("door command received while closing");
+            return;
+        }
+        if self.door == Door::Opening && status != Door::Open {
+            self.crash("door command received while opening");
+            return;
+        }
+        if self.door == Door::Open && status == Door::Opening {
+            self.crash("door already open");
+            return;
+        }
+        if self.door == Door::Closed && status == Door::Closing {
+            self.crash("door already closed");
+            return;
+        }
+        self.door = status;
+        self.clock = 0;
+    }
+
+    /// Handles one line of input, from the keyboard, the controller or the
+    /// clock (`T`), returning the event to send to the controller, if any.
+    pub fn handle_command(&mut self, cmd: &str) -> Option<Event> {
+        let command = Command::try_from(cmd.as_bytes()).ok();
+        if command == Some(Command::R) {
+            self.reset();
+            return None;
+        }
+        if self.crashed {
+            return None;
+        }
+        if cmd == "T" {
+            return self.handle_tick();
+        }
+        if let Some(command) = command {
+            self.handle_control(command);
+            return None;
+        }
+        match Event::try_from(cmd.as_bytes()) {
+            Ok(press) => self.handle_press(press),
+            Err(_) => {
+                self.crash("Unrecognized command");
+                None
+            }
+        }
+    }
+
+    // Button presses and the key switch, echoed to the controller.
+    fn handle_press(&mut self, press: Event) -> Option<Event> {
+        match press {
+            Event::PanelButtonPressed(n @ 1..=5) => self.set_panel_button(n as usize),
+            Event::ElevatorUp(n @ 1..=4) => self.set_up_button(n as usize),
+            Event::ElevatorUp(5) => {
+                self.crash("No up button on top floor");
+                return None;
+            }
+            Event::ElevatorDown(n @ 2..=5) => self.set_down_button(n as usize),
+            Event::ElevatorDown(1) => {
+                self.crash("No down button on bottom floor");
+                return None;
+            }
+            Event::KeySwitched(n @ 0..=2) => self.key = n as usize,
+            Event::LoadWeighed(n) => self.load = n as usize,
+            _ => {
+                self.crash("Unrecognized command");
+                return None;
+            }
+        }
+        Some(press)
+    }
+
+    // Commands from the controller.
+    fn handle_control(&mut self, command: Command) {
+        match command {
+            // Clear buttons
+            Command::CP(n @ 1..=5) => self.clear_panel_button(n as usize),
+            Command::CU(n @ 1..=4) => self.clear_up_button(n as usize),
+            Command::CU(5) => self.crash("No up button on top floor"),
+            Command::CD(n @ 2..=5) => self.clear_down_button(n as usize),
+            Command::CD(1) => self.crash("No down button on bottom floor"),
+            // Direction indicator lights
+            Command::IU(n @ 1..=4) => self.set_indicator(n as usize, Indicator::Up),
+            Command::IU(5) => self.crash("No up indicator light on top floor"),
+            Command::ID(n @ 2..=5) => self.set_indicator(n as usize, Indicator::Down),
+            Command::ID(1) => self.crash("No down indicator light on bottom floor"),
+            Command::CI(n @ 1..=5) => self.set_indicator(n as usize, Indicator::Off),
+            // Motor
+            Command::MU => self.set_motor(Motor::Up),
+            Command::MD => self.set_motor(Motor::Down),
+            Command::S => {
+                if self.stopping {
+                    self.crash("Already made a request to stop");
+                } else if self.motor != Motor::Off {
+                    // If we can safely stop we will.
+                    if self.clock <= TICKS_PER_FLOOR - APPROACH_TICKS {
+                        self.stopping = true;
+                    }
+                } else {
+                    self.crash("Request to stop, but not moving");
+                }
+            }
+            // Door
+            Command::DO => self.set_door(Door::Opening),
+            Command::DC => self.set_door(Door::Closing),
+            Command::R => self.reset(),
+            _ => self.crash("Unrecognized command"),
+        }
+    }
+
+    fn handle_tick(&mut self) -> Option<Event> {
+        self.clock += 1;
+        if self.motor == Motor::Up {
+            if self.floor >= 5 {
+                self.crash("Hit the roof!");
+            } else if self.clock == (TICKS_PER_FLOOR - APPROACH_TICKS) {
+                return Some(Event::ElevatorApproaching(self.floor as u8 + 1));
+            } else if self.clock >= TICKS_PER_FLOOR {
+                self.floor += 1;
+                self.clock = 0;
+                if self.stopping {
+                    self.set_motor(Motor::Off);
+                    self.stopping = false;
+                    return Some(Event::ElevatorStopped(self.floor as u8));
+                }
+            }
+        } else if self.motor == Motor::Down {
+            if self.floor <= 1 {
+                self.crash("Hit the ground!");
+            } else if self.clock == (TICKS_PER_FLOOR - APPROACH_TICKS) {
+                return Some(Event::ElevatorApproaching(self.floor as u8 - 1));
+            } else if self.clock >= TICKS_PER_FLOOR {
+                self.floor -= 1;
+                self.clock = 0;
+                if self.stopping {
+                    self.set_motor(Motor::Off);
+                    self.stopping = false;
+                    return Some(Event::ElevatorStopped(self.floor as u8));
+                }
+            }
+        } else if self.door == Door::Closing {
+            if self.clock > TICKS_FOR_DOOR {
+                self.set_door(Door::Closed);
+                return Some(Event::DoorClosed(self.floor as u8));
+            }
+        } else if self.door == Door::Opening && self.clock > TICKS_FOR_DOOR {
+            self.set_door(Door::Open);
+            return Some(Event::DoorOpened(self.floor as u8));
+        }
+        None
+    }
+}
@@ -0,0 +1,210 @@
+use crate::car::CarHandle;
+use crate::context::ElevatorContext;
+use crate::error::Anomaly;
+use crate::transition::State;
+use crate::types::plan::StepOutcome;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// A counter partitioned by a pre-rendered label set, e.g. `event="DoorOpened"`.
+#[derive(Debug, Default)]
+struct CounterFamily(std::sync::Mutex<BTreeMap<String, u64>>);
+
+impl CounterFamily {
+    fn inc(&self, labels: String) {
+        *self.0.lock().unwrap().entry(labels).or_default() += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        for (labels, value) in self.0.lock().unwrap().iter() {
+            let _ = writeln!(out, "{name}{{{labels}}} {value}");
+        }
+    }
+}
+
+/// Controller counters and gauges, exported in the Prometheus text format.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    events_received: CounterFamily,
+    invalid_packets: AtomicU64,
+    commands_sent: CounterFamily,
+    transitions: CounterFamily,
+    ignored_actions: CounterFamily,
+    anomalies: CounterFamily,
+    plan_steps: CounterFamily,
+    faults: AtomicU64,
+    state: std::sync::Mutex<Option<(State, Instant)>>,
+}
+
+impl Metrics {
+    pub fn record_event(&self, kind: &str) {
+        self.events_received.inc(format!("event=\"{kind}\""));
+    }
+
+    pub fn record_invalid_packet(&self) {
+        self.invalid_packets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_command(&self, name: &str) {
+        self.commands_sent.inc(format!("command=\"{name}\""));
+    }
+
+    pub fn record_transition(&self, from: &State, to: &State) {
+        self.transitions
+            .inc(format!("from=\"{from:?}\",to=\"{to:?}\""));
+        self.enter_state(*to);
+    }
+
+    pub fn record_ignored(&self, state: &State, action: &str) {
+        self.ignored_actions
+            .inc(format!("state=\"{state:?}\",action=\"{action}\""));
+    }
+
+    pub fn record_anomaly(&self, anomaly: &Anomaly) {
+        self.anomalies.inc(format!("kind=\"{}\"", anomaly.kind()));
+    }
+
+    pub fn record_step(&self, outcome: &StepOutcome) {
+        self.plan_steps
+            .inc(format!("outcome=\"{}\"", outcome.kind()));
+    }
+
+    pub fn record_fault(&self) {
+        self.faults.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn enter_state(&self, state: State) {
+        *self.state.lock().unwrap() = Some((state, Instant::now()));
+    }
+
+    pub fn render(&self, ctx: &ElevatorContext) -> String {
+        let mut out = String::new();
+        self.events_received.render(
+            &mut out,
+            "elevator_events_received_total",
+            "Hardware events received, by type.",
+        );
+        render_single(
+            &mut out,
+            "elevator_invalid_packets_total",
+            "counter",
+            "Packets rejected by the event parser.",
+            self.invalid_packets.load(Ordering::Relaxed),
+        );
+        self.commands_sent.render(
+            &mut out,
+            "elevator_commands_sent_total",
+            "Commands sent to the hardware, by type.",
+        );
+        self.transitions.render(
+            &mut out,
+            "elevator_state_transitions_total",
+            "State machine transitions, by source and target state.",
+        );
+        self.ignored_actions.render(
+            &mut out,
+            "elevator_ignored_actions_total",
+            "Scheduled actions the current state did not accept.",
+        );
+        self.anomalies.render(
+            &mut out,
+            "elevator_anomalies_total",
+            "Unexpected actions and hardware events, by kind.",
+        );
+        self.plan_steps.render(
+            &mut out,
+            "elevator_plan_steps_total",
+            "Planned steps, by whether they ran, went stale or failed.",
+        );
+        render_single(
+            &mut out,
+            "elevator_faults_total",
+            "counter",
+            "Actions that failed inside the controller.",
+            self.faults.load(Ordering::Relaxed),
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP elevator_queue_depth Pending requests, by queue."
+        );
+        let _ = writeln!(out, "# TYPE elevator_queue_depth gauge");
+        let _ = writeln!(
+            out,
+            "elevator_queue_depth{{queue=\"up\"}} {}",
+            ctx.up_queue.len()
+        );
+        let _ = writeln!(
+            out,
+            "elevator_queue_depth{{queue=\"down\"}} {}",
+            ctx.down_queue.len()
+        );
+
+        if let Some((state, since)) = self.state.lock().unwrap().as_ref() {
+            let _ = writeln!(out, "# HELP elevator_state Current controller state.");
+            let _ = writeln!(out, "# TYPE elevator_state gauge");
+            let _ = writeln!(out, "elevator_state{{state=\"{state:?}\"}} 1");
+            render_single(
+                &mut out,
+                "elevator_state_seconds",
+                "gauge",
+                "Seconds spent in the current state.",
+                since.elapsed().as_secs_f64(),
+            );
+        }
+        out
+    }
+}
+
+fn render_single(out: &mut String, name: &str, kind: &str, help: &str, value: impl Display) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+/// Serves `GET /metrics` on `address` until the listener fails.
+pub async fn serve(address: &str, metrics: Arc<Metrics>, car: CarHandle) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(address).await?;
+    println!("Metrics on http://{address}/metrics");
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    eprintln!("Metrics listener failed: {e:?}");
+                    return;
+                }
+            };
+            let metrics = metrics.clone();
+            let car = car.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                let len = stream.read(&mut buf).await.unwrap_or(0);
+                let response = if !buf[..len].starts_with(b"GET /metrics") {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_string()
+                } else if let Ok(snapshot) = car.snapshot().await {
+                    let body = metrics.render(&snapshot.context);
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                } else {
+                    "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_string()
+                };
+                if let Err(e) = stream.write_all(response.as_bytes()).await {
+                    eprintln!("Metrics write failed: {e:?}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
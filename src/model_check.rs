@@ -0,0 +1,311 @@
+use crate::command_channel::{self, COMMAND_CHANNEL_CAPACITY};
+use crate::context::ElevatorContext;
+use crate::lifty::{Door, Elevator, TICKS_FOR_DOOR, TICKS_PER_FLOOR};
+use crate::strategies::scan::ScanStrategy;
+use crate::transition::State;
+use crate::types::event::Event;
+use crate::types::plan::Plan;
+use crate::types::sched_events::{Action, ScheduleEvent};
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// Every hardware event is at most this many ticks away while something is in motion.
+const QUIET_TICKS: usize = TICKS_PER_FLOOR + TICKS_FOR_DOOR + 1;
+
+/// Button presses a resident can make without crashing Lifty on their own.
+const PRESSES: [&str; 13] = [
+    "P1", "P2", "P3", "P4", "P5", "U1", "U2", "U3", "U4", "D2", "D3", "D4", "D5",
+];
+
+/// One choice the explorer makes between controller and hardware.
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// A resident presses a button.
+    Press(&'static str),
+    /// The clock runs until the hardware reports an event.
+    Hardware,
+    /// The controller's pending delayed action (e.g. closing the door) fires.
+    Timer,
+}
+
+#[derive(Debug, Clone)]
+pub enum Violation {
+    /// Lifty crashed because of a command the controller sent.
+    Crash(&'static str),
+    /// Nothing can happen any more, yet these floors still wait for the door to open.
+    Stuck(BTreeSet<u8>),
+}
+
+#[derive(Debug)]
+pub struct Counterexample {
+    /// Each step with Lifty's status line after it.
+    pub trace: Vec<(Step, String)>,
+    pub violation: Violation,
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (step, status)) in self.trace.iter().enumerate() {
+            writeln!(f, "{:>3}. {:<12} {status}", i + 1, format!("{step:?}"))?;
+        }
+        match &self.violation {
+            Violation::Crash(reason) => write!(f, "CRASH: {reason}"),
+            Violation::Stuck(floors) => write!(f, "STUCK with calls pending for {floors:?}"),
+        }
+    }
+}
+
+pub struct Report {
+    pub states: usize,
+    pub counterexample: Option<Counterexample>,
+}
+
+/// Simulator and controller composed into one state, advanced one `Step` at a time.
+#[derive(Clone)]
+struct World {
+    sim: Elevator,
+    ctx: ElevatorContext,
+    state: State,
+    /// The rest of a plan waiting on its head `WaitTime`.
+    delayed: Plan<ScheduleEvent>,
+    /// Events that arrived while the controller was waiting.
+    inbox: VecDeque<Event>,
+    /// Floors pressed but not yet served by a door opening there.
+    outstanding: BTreeSet<u8>,
+    presses: usize,
+}
+
+impl World {
+    fn new(min_floor: u8, max_floor: u8) -> Self {
+        World {
+            sim: Elevator::new(),
+            ctx: ElevatorContext::new(min_floor, max_floor),
+            state: State::Idle,
+            delayed: Plan::new(),
+            inbox: VecDeque::new(),
+            outstanding: BTreeSet::new(),
+            presses: 0,
+        }
+    }
+
+    /// Hash of everything that affects future behaviour. Button lamps are left
+    /// out: the controller never reads them and Lifty only displays them.
+    fn key(&self) -> u64 {
+        let mut up: Vec<_> = self.ctx.up_queue.iter().map(|&Reverse(f)| f).collect();
+        up.sort();
+        let mut down: Vec<_> = self.ctx.down_queue.iter().copied().collect();
+        down.sort();
+        let sim = &self.sim;
+        let mut hasher = DefaultHasher::new();
+        (
+            sim.floor,
+            sim.clock,
+            &sim.motor,
+            &sim.door,
+            sim.stopping,
+            &sim.indicator,
+            sim.indicator_floor,
+        )
+            .hash(&mut hasher);
+        (
+            self.ctx.position,
+            self.ctx.direction_up,
+            up,
+            down,
+            &self.ctx.calls,
+            self.ctx.load,
+            self.ctx.active_target,
+            self.state as u8,
+            format!("{:?}|{:?}", self.delayed, self.inbox),
+            &self.outstanding,
+            self.presses,
+        )
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn step(&mut self, rt: &Runtime, step: &Step) -> bool {
+        match step {
+            Step::Press(button) => {
+                self.presses += 1;
+                let Some(event) = self.sim.handle_command(button) else {
+                    return false;
+                };
+                if let Event::PanelButtonPressed(f)
+                | Event::ElevatorUp(f)
+                | Event::ElevatorDown(f) = event
+                {
+                    // Pressing the floor the open door is on needs no further service.
+                    let served = self.sim.door == Door::Open && self.sim.floor == f as usize;
+                    if !served {
+                        self.outstanding.insert(f);
+                    }
+                }
+                self.deliver(rt, event);
+            }
+            Step::Hardware => {
+                let Some(event) = self.tick_until_event() else {
+                    return self.sim.crashed;
+                };
+                if let Event::DoorOpened(f) = event {
+                    self.outstanding.remove(&f);
+                }
+                self.deliver(rt, event);
+            }
+            Step::Timer => {
+                let Some(step) = self.delayed.next_step() else {
+                    return false;
+                };
+                let ScheduleEvent::WaitTime(_, action) = step.event else {
+                    return false;
+                };
+                if step.violated(self.state, &self.ctx).is_none() {
+                    self.apply(rt, action);
+                }
+                let rest = std::mem::take(&mut self.delayed);
+                self.run_plan(rt, rest);
+                while self.delayed.is_empty()
+                    && let Some(event) = self.inbox.pop_front()
+                {
+                    self.deliver(rt, event);
+                }
+            }
+        }
+        true
+    }
+
+    fn tick_until_event(&mut self) -> Option<Event> {
+        for _ in 0..QUIET_TICKS {
+            if let Some(event) = self.sim.handle_command("T") {
+                return Some(event);
+            }
+            if self.sim.crashed {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn deliver(&mut self, rt: &Runtime, event: Event) {
+        if !self.delayed.is_empty() {
+            self.inbox.push_back(event);
+            return;
+        }
+        if let Some(plan) = ScanStrategy::plan(event, &mut self.ctx, self.state).plan {
+            self.run_plan(rt, plan);
+        }
+    }
+
+    fn run_plan(&mut self, rt: &Runtime, mut plan: Plan<ScheduleEvent>) {
+        while let Some(step) = plan.next_step() {
+            match step.event {
+                ScheduleEvent::WaitTime(..) => {
+                    plan.push_front(step);
+                    self.delayed = plan;
+                    return;
+                }
+                _ if step.violated(self.state, &self.ctx).is_some() => {}
+                ScheduleEvent::Instant(action) => self.apply(rt, action),
+                ScheduleEvent::Anomaly(_) => {}
+            }
+        }
+    }
+
+    fn apply(&mut self, rt: &Runtime, action: Action) {
+        let (tx, rx) = command_channel::channel(COMMAND_CHANNEL_CAPACITY);
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = sent.clone();
+        let _runtime = rt.enter();
+        command_channel::spawn_loopback(rx, move |batch| sink.lock().unwrap().extend(batch));
+        let machine = self.state.enter(tx);
+        self.state = match rt.block_on(machine.on_event(action, &mut self.ctx)) {
+            Ok(next) => next.state(),
+            Err(e) => e.machine.state(),
+        };
+        for command in sent.lock().unwrap().drain(..) {
+            self.sim.handle_command(&command.to_string());
+        }
+    }
+
+    fn violation(&self) -> Option<Violation> {
+        if let Some(reason) = self.sim.crash_reason {
+            return Some(Violation::Crash(reason));
+        }
+        let quiescent = self.delayed.is_empty()
+            && self.inbox.is_empty()
+            && self.clone().tick_until_event().is_none();
+        (quiescent && !self.outstanding.is_empty())
+            .then(|| Violation::Stuck(self.outstanding.clone()))
+    }
+}
+
+/// Breadth-first search over every interleaving of presses, hardware events
+/// and timers up to `depth` steps and `max_presses` presses, so the first
+/// counterexample found is a shortest one.
+pub fn explore(min_floor: u8, max_floor: u8, depth: usize, max_presses: usize) -> Report {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .expect("failed to build runtime");
+    let steps: Vec<Step> = PRESSES
+        .iter()
+        .map(|&p| Step::Press(p))
+        .chain([Step::Hardware, Step::Timer])
+        .collect();
+
+    // Traces are kept as parent links: (parent, step, Lifty status after the step).
+    let mut nodes: Vec<(usize, Step, String)> = Vec::new();
+    let trace_to = |nodes: &[(usize, Step, String)], mut index: usize| {
+        let mut trace = Vec::new();
+        while index != usize::MAX {
+            let (parent, step, status) = &nodes[index];
+            trace.push((step.clone(), status.clone()));
+            index = *parent;
+        }
+        trace.reverse();
+        trace
+    };
+
+    let initial = World::new(min_floor, max_floor);
+    let mut visited = HashSet::from([initial.key()]);
+    let mut frontier = VecDeque::from([(initial, usize::MAX, 0)]);
+
+    while let Some((world, node, len)) = frontier.pop_front() {
+        if len >= depth {
+            continue;
+        }
+        for step in &steps {
+            if matches!(step, Step::Press(_)) && world.presses >= max_presses {
+                continue;
+            }
+            let mut next = world.clone();
+            if !next.step(&rt, step) {
+                continue;
+            }
+            let violation = next.violation();
+            if violation.is_none() && !visited.insert(next.key()) {
+                continue;
+            }
+            nodes.push((node, step.clone(), next.sim.as_string()));
+            if let Some(violation) = violation {
+                return Report {
+                    states: visited.len(),
+                    counterexample: Some(Counterexample {
+                        trace: trace_to(&nodes, nodes.len() - 1),
+                        violation,
+                    }),
+                };
+            }
+            frontier.push_back((next, nodes.len() - 1, len + 1));
+        }
+    }
+    Report {
+        states: visited.len(),
+        counterexample: None,
+    }
+}
@@ -0,0 +1,367 @@
+// Simulated residents for the `lify` simulator: they turn up at floors,
+// press the buttons Lifty has, ride the car and get off, so waiting times
+// can be measured per passenger instead of per button. The car's load
+// sensor reports what they weigh together.
+
+use crate::lifty::{Door, Elevator, MAX_FLOOR, MIN_FLOOR, Motor, TICK_INTERVAL};
+use crate::position::Direction;
+use crate::types::event::Event;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+/// The floor everyone enters the building at during an up-peak.
+pub const LOBBY: u8 = MIN_FLOOR;
+
+const TICKS_PER_MINUTE: f64 = 60_000.0 / TICK_INTERVAL as f64;
+
+/// The car's rated capacity, in percent as the load sensor reports it.
+/// Riders stop getting on at it; one too many steps off again.
+const CAPACITY: u8 = 100;
+
+/// What generated passengers weigh, in percent of the car's capacity.
+const WEIGHT: (u8, u8) = (6, 12);
+
+/// How long an overloaded car's door stays open before the last one in
+/// gives up their place, a while after the controller held the door for it.
+const STEP_OFF_TICKS: usize = 30;
+
+/// Where passengers come from and go to. Arrivals are a Poisson process in
+/// every case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Traffic {
+    /// Any floor to any other.
+    Poisson,
+    /// From the lobby up to the other floors, as in the morning.
+    UpPeak,
+    /// Between the floors above the lobby.
+    Interfloor,
+}
+
+impl FromStr for Traffic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "poisson" => Ok(Traffic::Poisson),
+            "up-peak" => Ok(Traffic::UpPeak),
+            "interfloor" => Ok(Traffic::Interfloor),
+            other => Err(format!(
+                "unknown traffic {other:?}, expected poisson, up-peak or interfloor"
+            )),
+        }
+    }
+}
+
+/// Xorshift, so runs are reproducible from a seed without pulling in a crate.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    /// Spreads the seed's bits first: xorshift started from a small seed
+    /// gives small numbers for a while.
+    fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Rng((z ^ (z >> 31)).max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform in `(0, 1]`.
+    fn unit(&mut self) -> f64 {
+        ((self.next() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    fn floor(&mut self, low: u8, high: u8) -> u8 {
+        low + (self.next() % (high - low + 1) as u64) as u8
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Passenger {
+    pub id: usize,
+    pub origin: u8,
+    pub destination: u8,
+    /// Percent of the car's capacity.
+    pub weight: u8,
+    /// Ticks since the simulation started.
+    pub arrived_at: u64,
+    pub boarded_at: Option<u64>,
+    pub alighted_at: Option<u64>,
+}
+
+impl Passenger {
+    pub fn direction(&self) -> Direction {
+        Direction::from_up(self.destination > self.origin)
+    }
+
+    /// Ticks spent waiting for the car, once aboard.
+    pub fn waiting_ticks(&self) -> Option<u64> {
+        Some(self.boarded_at? - self.arrived_at)
+    }
+
+    /// Ticks from arriving to getting off, once delivered.
+    pub fn journey_ticks(&self) -> Option<u64> {
+        Some(self.alighted_at? - self.arrived_at)
+    }
+}
+
+/// Waiting times of the delivered passengers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    pub delivered: usize,
+    pub waiting: usize,
+    pub riding: usize,
+    pub mean_wait_secs: f64,
+    pub max_wait_secs: f64,
+    pub mean_journey_secs: f64,
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "delivered {} (waiting {}, riding {}): wait mean {:.1}s max {:.1}s, journey mean {:.1}s",
+            self.delivered,
+            self.waiting,
+            self.riding,
+            self.mean_wait_secs,
+            self.max_wait_secs,
+            self.mean_journey_secs
+        )
+    }
+}
+
+fn secs(ticks: u64) -> f64 {
+    (ticks * TICK_INTERVAL) as f64 / 1000.0
+}
+
+#[derive(Debug, Clone)]
+pub struct Passengers {
+    traffic: Traffic,
+    per_tick: f64,
+    rng: Rng,
+    clock: u64,
+    next_arrival: u64,
+    next_id: usize,
+    /// The way the car was last seen going, which passengers read off its
+    /// motion since the controller leaves the indicator lights alone.
+    heading: Option<Direction>,
+    /// Those who stepped off an overloaded car and wait for the next one.
+    stepped_off: BTreeSet<usize>,
+    pub waiting: Vec<Passenger>,
+    pub riding: Vec<Passenger>,
+    pub delivered: Vec<Passenger>,
+}
+
+impl Passengers {
+    /// Passengers arriving at `per_minute` on average.
+    pub fn new(traffic: Traffic, per_minute: f64, seed: u64) -> Self {
+        let mut passengers = Passengers {
+            traffic,
+            per_tick: per_minute / TICKS_PER_MINUTE,
+            rng: Rng::new(seed),
+            clock: 0,
+            next_arrival: 0,
+            next_id: 1,
+            heading: None,
+            stepped_off: BTreeSet::new(),
+            waiting: Vec::new(),
+            riding: Vec::new(),
+            delivered: Vec::new(),
+        };
+        passengers.next_arrival = passengers.interval();
+        passengers
+    }
+
+    /// Ticks until the next arrival, exponentially distributed. At least one,
+    /// so however high the rate, each tick ends.
+    fn interval(&mut self) -> u64 {
+        if self.per_tick.is_nan() || self.per_tick <= 0.0 {
+            return u64::MAX;
+        }
+        ((-self.rng.unit().ln() / self.per_tick).ceil() as u64).max(1)
+    }
+
+    fn journey(&mut self) -> (u8, u8) {
+        let (low, high) = match self.traffic {
+            Traffic::Poisson => (MIN_FLOOR, MAX_FLOOR),
+            Traffic::UpPeak => return (LOBBY, self.rng.floor(LOBBY + 1, MAX_FLOOR)),
+            Traffic::Interfloor => (LOBBY + 1, MAX_FLOOR),
+        };
+        let origin = self.rng.floor(low, high);
+        // One floor fewer to pick from, skipping the origin.
+        let destination = self.rng.floor(low, high - 1);
+        (origin, destination + (destination >= origin) as u8)
+    }
+
+    /// Someone turns up at `origin` wanting to go to `destination`.
+    pub fn arrive(&mut self, origin: u8, destination: u8, weight: u8) {
+        self.waiting.push(Passenger {
+            id: self.next_id,
+            origin,
+            destination,
+            weight,
+            arrived_at: self.clock,
+            boarded_at: None,
+            alighted_at: None,
+        });
+        self.next_id += 1;
+    }
+
+    /// Advances one clock tick against the car's state and returns the
+    /// buttons pressed, to be handled by Lifty like keyboard presses.
+    pub fn tick(&mut self, elevator: &Elevator) -> Vec<Event> {
+        self.clock += 1;
+        while self.clock >= self.next_arrival {
+            let (origin, destination) = self.journey();
+            let weight = self.rng.floor(WEIGHT.0, WEIGHT.1);
+            self.arrive(origin, destination, weight);
+            self.next_arrival = self.next_arrival.saturating_add(self.interval());
+        }
+
+        let floor = elevator.floor as u8;
+        self.heading = match elevator.motor {
+            Motor::Up => Some(Direction::Up),
+            Motor::Down => Some(Direction::Down),
+            Motor::Off if floor == MIN_FLOOR => Some(Direction::Up),
+            Motor::Off if floor == MAX_FLOOR => Some(Direction::Down),
+            Motor::Off => self.heading,
+        };
+        if elevator.door == Door::Open {
+            self.exchange(floor);
+            if elevator.load > CAPACITY as usize && elevator.clock > STEP_OFF_TICKS {
+                self.step_off();
+            }
+        } else {
+            self.stepped_off.clear();
+        }
+        let mut presses = self.presses(elevator);
+        if self.load() as usize != elevator.load {
+            presses.insert(0, Event::LoadWeighed(self.load()));
+        }
+        presses
+    }
+
+    /// What the riders weigh together, in percent of capacity.
+    pub fn load(&self) -> u8 {
+        let total: u32 = self.riding.iter().map(|p| p.weight as u32).sum();
+        total.min(u8::MAX as u32) as u8
+    }
+
+    /// The last one in leaves the overloaded car to wait for the next.
+    fn step_off(&mut self) {
+        let Some(mut passenger) = self.riding.pop() else {
+            return;
+        };
+        println!("passenger {} steps off the overloaded car", passenger.id);
+        passenger.boarded_at = None;
+        self.stepped_off.insert(passenger.id);
+        self.waiting.insert(0, passenger);
+    }
+
+    /// Riders for `floor` get off; those waiting there board if the car is
+    /// going their way, or is empty and so free to take them, until it is
+    /// full. The last one in may squeeze past capacity.
+    fn exchange(&mut self, floor: u8) {
+        let clock = self.clock;
+        let (mut arrived, riding) = std::mem::take(&mut self.riding)
+            .into_iter()
+            .partition(|p: &Passenger| p.destination == floor);
+        self.riding = riding;
+        for passenger in &mut arrived {
+            passenger.alighted_at = Some(clock);
+            println!(
+                "passenger {} {}->{} waited {:.1}s, arrived after {:.1}s",
+                passenger.id,
+                passenger.origin,
+                passenger.destination,
+                secs(passenger.waiting_ticks().unwrap_or_default()),
+                secs(passenger.journey_ticks().unwrap_or_default()),
+            );
+        }
+        self.delivered.append(&mut arrived);
+
+        let empty = self.riding.is_empty();
+        let heading = self.heading;
+        // Nobody gets on once the buzzer has made someone get off.
+        let mut index = 0;
+        while index < self.waiting.len() && self.load() < CAPACITY && self.stepped_off.is_empty() {
+            let p = &self.waiting[index];
+            if p.origin == floor && (empty || heading.is_none_or(|d| d == p.direction())) {
+                let mut passenger = self.waiting.remove(index);
+                passenger.boarded_at = Some(clock);
+                self.riding.push(passenger);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    /// Everyone presses their button if it isn't lit, unless the door opening
+    /// in front of them already serves them.
+    fn presses(&self, elevator: &Elevator) -> Vec<Event> {
+        let open_at =
+            matches!(elevator.door, Door::Opening | Door::Open).then_some(elevator.floor as u8);
+        let mut presses = Vec::new();
+        for passenger in &self.riding {
+            let floor = passenger.destination;
+            let press = Event::PanelButtonPressed(floor);
+            if !elevator.panel_buttons[floor as usize - 1]
+                && open_at != Some(floor)
+                && !presses.contains(&press)
+            {
+                presses.push(press);
+            }
+        }
+        for passenger in &self.waiting {
+            let floor = passenger.origin;
+            let (lit, press) = match passenger.direction() {
+                Direction::Up => (
+                    elevator.up_buttons[floor as usize - 1],
+                    Event::ElevatorUp(floor),
+                ),
+                Direction::Down => (
+                    elevator.down_buttons[floor as usize - 1],
+                    Event::ElevatorDown(floor),
+                ),
+            };
+            if !lit && open_at != Some(floor) && !presses.contains(&press) {
+                presses.push(press);
+            }
+        }
+        presses
+    }
+
+    pub fn summary(&self) -> Summary {
+        let waits: Vec<_> = (self.delivered.iter().chain(&self.riding))
+            .filter_map(|p| p.waiting_ticks())
+            .map(secs)
+            .collect();
+        let journeys: Vec<_> = (self.delivered.iter())
+            .filter_map(|p| p.journey_ticks())
+            .map(secs)
+            .collect();
+        let mean = |values: &[f64]| {
+            if values.is_empty() {
+                0.0
+            } else {
+                values.iter().sum::<f64>() / values.len() as f64
+            }
+        };
+        Summary {
+            delivered: self.delivered.len(),
+            waiting: self.waiting.len(),
+            riding: self.riding.len(),
+            mean_wait_secs: mean(&waits),
+            max_wait_secs: waits.iter().copied().fold(0.0, f64::max),
+            mean_journey_secs: mean(&journeys),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::context::Location;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Direction {
+    Down,
+    Up,
+}
+
+impl Direction {
+    pub fn from_up(up: bool) -> Self {
+        if up { Direction::Up } else { Direction::Down }
+    }
+
+    pub fn is_up(self) -> bool {
+        self == Direction::Up
+    }
+
+    /// The floor after `floor` in this direction, if there is one.
+    pub fn step(self, floor: u8) -> Option<u8> {
+        match self {
+            Direction::Up => floor.checked_add(1),
+            Direction::Down => floor.checked_sub(1),
+        }
+    }
+}
+
+/// Where in a move the car is, in the order a move goes through them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Phase {
+    Stopped,
+    /// Off a floor, before the next floor's approach.
+    Departing,
+    /// Lifty reported the approach (`An`). It honours `S` only until its
+    /// next tick (`clock <= TICKS_PER_FLOOR - APPROACH_TICKS`), so the floor
+    /// can still be stopped at while the approach is being handled.
+    Approaching,
+    /// Told to stop, settling into the floor.
+    Levelling,
+}
+
+/// The car's place in the shaft. Floors only ever come from hardware events;
+/// the commands the controller sends only move it on to the next phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Position {
+    Stopped(u8),
+    Departing { from: u8, direction: Direction },
+    Approaching { floor: u8, direction: Direction },
+    Levelling { floor: u8, direction: Direction },
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::Stopped(0)
+    }
+}
+
+impl Position {
+    pub fn phase(&self) -> Phase {
+        match self {
+            Position::Stopped(_) => Phase::Stopped,
+            Position::Departing { .. } => Phase::Departing,
+            Position::Approaching { .. } => Phase::Approaching,
+            Position::Levelling { .. } => Phase::Levelling,
+        }
+    }
+
+    /// The direction of travel, or `None` while stopped.
+    pub fn direction(&self) -> Option<Direction> {
+        match *self {
+            Position::Stopped(_) => None,
+            Position::Departing { direction, .. }
+            | Position::Approaching { direction, .. }
+            | Position::Levelling { direction, .. } => Some(direction),
+        }
+    }
+
+    /// The floor the car stands at or will reach next.
+    pub fn next_floor(&self) -> Option<u8> {
+        match *self {
+            Position::Stopped(floor)
+            | Position::Approaching { floor, .. }
+            | Position::Levelling { floor, .. } => Some(floor),
+            Position::Departing { from, direction } => direction.step(from),
+        }
+    }
+
+    /// Height above the ground in half floors: a floor is even, the span
+    /// between two floors odd.
+    fn half_floors(&self) -> u16 {
+        let at = |floor: u8| 2 * floor as u16;
+        match *self {
+            Position::Stopped(floor) => at(floor),
+            Position::Departing { from, direction } => match direction {
+                Direction::Up => at(from) + 1,
+                Direction::Down => at(from).saturating_sub(1),
+            },
+            Position::Approaching { floor, direction }
+            | Position::Levelling { floor, direction } => match direction {
+                Direction::Up => at(floor).saturating_sub(1),
+                Direction::Down => at(floor) + 1,
+            },
+        }
+    }
+
+    pub fn location(&self) -> Location {
+        let half_floors = self.half_floors();
+        let floor = (half_floors / 2) as u8;
+        if half_floors.is_multiple_of(2) {
+            Location::AtFloor(floor)
+        } else {
+            Location::BetweenFloors(floor, floor + 1)
+        }
+    }
+
+    /// Whether the car can still come to rest at `floor` without reversing.
+    pub fn can_stop_at(&self, floor: u8) -> bool {
+        let ahead = |from: u8, direction: Direction| match direction {
+            Direction::Up => floor > from,
+            Direction::Down => floor < from,
+        };
+        match *self {
+            Position::Stopped(at) => floor == at,
+            Position::Departing { from, direction } => ahead(from, direction),
+            Position::Approaching {
+                floor: approached,
+                direction,
+            } => floor == approached || ahead(approached, direction),
+            Position::Levelling { floor: target, .. } => floor == target,
+        }
+    }
+}
+
+/// Positions order by height in the shaft; at the same height, by phase and
+/// then direction.
+impl Ord for Position {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.half_floors(), self.phase(), self.direction()).cmp(&(
+            other.half_floors(),
+            other.phase(),
+            other.direction(),
+        ))
+    }
+}
+
+impl PartialOrd for Position {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -1,79 +1,171 @@
-use crate::transition::BoxedTransition;
-use crate::types::cmd::Command;
+use crate::command_channel::{CommandReceiver, PendingBatch};
+use crate::config::FlushPolicy;
+use crate::error::{Anomaly, Error};
+use crate::types::cmd::{Command, CommandBatch};
 use crate::types::sched_events::Action;
 
-use crate::context::ElevatorContext;
-use futures::ready;
+use crate::car::{Applied, CarHandle};
+use crate::metrics::Metrics;
+use crate::services::readiness::CallSlot;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::Poll;
 use tokio::net::UdpSocket;
-use tokio::sync::Mutex;
 use tower::Service;
 
+#[derive(Clone)]
 pub struct ControllerService {
-    is_ready: Arc<Mutex<bool>>,
-    transition: Arc<Mutex<Option<BoxedTransition>>>,
-    elevator_context: Arc<Mutex<ElevatorContext>>,
+    /// Closed until the transport is up, then held by each transition.
+    slot: CallSlot,
+    car: CarHandle,
+    metrics: Arc<Metrics>,
+    strict: bool,
 }
 
 impl ControllerService {
-    pub fn new(
-        transition: Arc<Mutex<Option<BoxedTransition>>>,
-        elevator_context: Arc<Mutex<ElevatorContext>>,
-    ) -> Self {
+    pub fn new(car: CarHandle, metrics: Arc<Metrics>, strict: bool) -> Self {
         ControllerService {
-            is_ready: Arc::new(Mutex::new(false)),
-            transition,
-            elevator_context,
+            slot: CallSlot::closed(),
+            car,
+            metrics,
+            strict,
         }
     }
 
+    /// Writes queued batches to the socket, confirming each one once its
+    /// datagram is out. A socket error fails the waiting transition instead
+    /// of taking the task down.
     pub async fn run_background(
         &self,
         socket: Arc<UdpSocket>,
-        mut rx: tokio::sync::mpsc::UnboundedReceiver<Command>,
+        mut rx: CommandReceiver,
         address: &'static str,
+        flush: FlushPolicy,
     ) -> anyhow::Result<()> {
+        let metrics = self.metrics.clone();
         tokio::spawn(async move {
-            while let Some(cmd) = rx.recv().await {
-                socket
-                    .send_to(cmd.to_string().as_bytes(), address)
-                    .await
-                    .expect("failed to send command");
+            while let Some(pending) = rx.recv().await {
+                // Once a datagram fails, the ones queued behind it are not
+                // sent, so the hardware never sees a batch out of order.
+                let mut failure = None;
+                for (datagram, pending) in datagrams(pending, &mut rx, flush) {
+                    if failure.is_none() {
+                        let bytes = Command::encode_batch(&datagram);
+                        match socket.send_to(&bytes, address).await {
+                            Ok(_) => {
+                                for cmd in &datagram {
+                                    metrics.record_command(cmd.name());
+                                }
+                            }
+                            Err(e) => {
+                                eprintln!("Failed to send {datagram:?}: {e}");
+                                failure = Some(Error::Transport(e.to_string()));
+                            }
+                        }
+                    }
+                    for pending in pending {
+                        pending.confirm(failure.clone().map_or(Ok(()), Err));
+                    }
+                }
             }
         });
-        *self.is_ready.lock().await = true;
+        self.transport_up();
         Ok(())
     }
+
+    /// Marks the transport as up. `run_background` does this for UDP; other
+    /// transports (e.g. a loopback in tests) call it themselves.
+    pub fn transport_up(&self) {
+        self.slot.opener().open();
+    }
+}
+
+/// Splits a pending batch, plus any batches already queued when coalescing,
+/// into datagrams, each with the batches it completes.
+fn datagrams(
+    first: PendingBatch,
+    rx: &mut CommandReceiver,
+    flush: FlushPolicy,
+) -> Vec<(CommandBatch, Vec<PendingBatch>)> {
+    match flush {
+        FlushPolicy::EachCommand => {
+            let mut datagrams: Vec<_> = first
+                .batch
+                .iter()
+                .map(|&cmd| (vec![cmd], Vec::new()))
+                .collect();
+            // The batch is only confirmed once its last command is out.
+            match datagrams.last_mut() {
+                Some((_, confirms)) => confirms.push(first),
+                None => first.confirm(Ok(())),
+            }
+            datagrams
+        }
+        FlushPolicy::EachBatch => vec![(first.batch.clone(), vec![first])],
+        FlushPolicy::Coalesce { max_bytes } => {
+            let mut datagram = first.batch.clone();
+            let mut pending = vec![first];
+            let mut datagrams = Vec::new();
+            while let Ok(next) = rx.try_recv() {
+                let mut merged = datagram.clone();
+                merged.extend_from_slice(&next.batch);
+                if Command::encode_batch(&merged).len() <= max_bytes {
+                    datagram = merged;
+                    pending.push(next);
+                } else {
+                    datagrams.push((
+                        std::mem::replace(&mut datagram, next.batch.clone()),
+                        std::mem::replace(&mut pending, vec![next]),
+                    ));
+                }
+            }
+            datagrams.push((datagram, pending));
+            datagrams
+        }
+    }
 }
 
 impl Service<Action> for ControllerService {
     type Response = ();
-    type Error = anyhow::Error;
+    type Error = Error;
     type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
 
     fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
-        let mut lock_fut = Box::pin(self.is_ready.lock());
-        let guard = ready!(lock_fut.as_mut().poll(cx));
-        if !*guard {
-            return Poll::Pending;
-        }
-        Poll::Ready(Ok(()))
+        self.slot.poll_acquire(cx).map(Ok)
     }
 
     fn call(&mut self, action: Action) -> Self::Future {
-        let transition = Arc::clone(&self.transition);
-        let elevator_context = Arc::clone(&self.elevator_context);
+        let slot = self.slot.take();
+        let car = self.car.clone();
+        let metrics = Arc::clone(&self.metrics);
+        let strict = self.strict;
         Box::pin(async move {
-            let mut guard = transition.lock().await;
-            let current = guard
-                .take()
-                .ok_or_else(|| anyhow::anyhow!("Transition was None"))?;
-            let mut ctx = elevator_context.lock().await;
-            let next = current.on_event(action, &mut ctx).await?;
-            *guard = Some(next);
-            Ok(())
+            let _slot = slot;
+            let Applied {
+                from,
+                legal,
+                result,
+            } = car.apply(action, strict).await?;
+            if !legal {
+                metrics.record_anomaly(&Anomaly::IllegalTransition {
+                    state: from,
+                    action,
+                });
+            }
+            match result {
+                Ok(_) if !legal => {
+                    metrics.record_ignored(&from, &format!("{action:?}"));
+                    Ok(())
+                }
+                Ok(to) => {
+                    metrics.record_transition(&from, &to);
+                    Ok(())
+                }
+                Err(error) => {
+                    metrics.record_fault();
+                    Err(error)
+                }
+            }
         })
     }
 }
@@ -1,3 +1,4 @@
 pub mod controller;
+pub mod readiness;
 pub mod scheduler;
 pub mod udp_event;
@@ -0,0 +1,108 @@
+use futures::ready;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::PollSemaphore;
+
+/// Lets one call at a time through a service. `poll_ready` waits for the
+/// slot with the waker registered, and `call` carries the slot in its
+/// future, so the service only reports ready again once that call finished.
+pub struct CallSlot {
+    semaphore: PollSemaphore,
+    permit: Option<OwnedSemaphorePermit>,
+    opened: Arc<AtomicBool>,
+}
+
+impl CallSlot {
+    pub fn new() -> Self {
+        Self::with_permits(1)
+    }
+
+    /// A slot that stays closed until `open` is called, e.g. until the
+    /// transport behind the service is up.
+    pub fn closed() -> Self {
+        Self::with_permits(0)
+    }
+
+    fn with_permits(permits: usize) -> Self {
+        Self {
+            semaphore: PollSemaphore::new(Arc::new(Semaphore::new(permits))),
+            permit: None,
+            opened: Arc::new(AtomicBool::new(permits > 0)),
+        }
+    }
+
+    pub fn opener(&self) -> SlotOpener {
+        SlotOpener {
+            semaphore: self.semaphore.clone_inner(),
+            opened: self.opened.clone(),
+        }
+    }
+
+    pub fn poll_acquire(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.permit.is_none() {
+            // The semaphore is never closed, so a permit always comes.
+            self.permit = ready!(self.semaphore.poll_acquire(cx));
+        }
+        Poll::Ready(())
+    }
+
+    /// Waits for the slot outside of `poll_ready`, e.g. for work that
+    /// continues a call after the call returned.
+    pub async fn acquire(&self) -> OwnedSemaphorePermit {
+        self.semaphore
+            .clone_inner()
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed")
+    }
+
+    /// Takes the slot acquired by `poll_ready` for the call's future.
+    pub fn take(&mut self) -> OwnedSemaphorePermit {
+        self.permit
+            .take()
+            .expect("poll_ready must return Ready before call")
+    }
+}
+
+/// Clones share the slot but not the permit, like tower's `ConcurrencyLimit`.
+impl Clone for CallSlot {
+    fn clone(&self) -> Self {
+        Self {
+            semaphore: self.semaphore.clone(),
+            permit: None,
+            opened: self.opened.clone(),
+        }
+    }
+}
+
+impl Default for CallSlot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Opens a `closed` slot, waking whoever waits in `poll_ready`.
+#[derive(Debug, Clone)]
+pub struct SlotOpener {
+    semaphore: Arc<Semaphore>,
+    opened: Arc<AtomicBool>,
+}
+
+impl SlotOpener {
+    /// Only the first call adds the permit; the slot stays one call wide.
+    pub fn open(&self) {
+        if !self.opened.swap(true, Ordering::SeqCst) {
+            self.semaphore.add_permits(1);
+        }
+    }
+}
+
+/// Takes the service `poll_ready` just readied, leaving a fresh clone behind
+/// for the next call, so a call's future owns its copy instead of sharing
+/// one behind a lock.
+pub fn take_ready<S: Clone>(service: &mut S) -> S {
+    let clone = service.clone();
+    std::mem::replace(service, clone)
+}
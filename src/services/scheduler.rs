@@ -1,42 +1,88 @@
+use futures::ready;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use tokio::sync::Mutex;
-use tower::{Layer, Service};
+use std::time::Duration;
+use tower::{Layer, Service, ServiceExt};
 
+use crate::car::CarHandle;
+use crate::config::{ControllerConfig, FaultPolicy};
+use crate::decision::DecisionLog;
+use crate::error::{Error, ErrorClass};
+use crate::metrics::Metrics;
+use crate::services::readiness::{CallSlot, take_ready};
 use crate::strategy::Strategy;
-use crate::transition::SharedStateMachine;
 use crate::types::event::Event;
+use crate::types::plan::{Plan, PlanReport, PlanStep, StepOutcome};
 use crate::types::sched_events::{Action, ScheduleEvent};
 
+/// Retries of a transient failure before the action is given up on.
+pub const MAX_RETRIES: u32 = 3;
+/// Wait before the first retry; each further retry waits this much longer.
+pub const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+#[derive(Clone)]
 pub struct SchedulerService<S, ST> {
-    inner: Arc<Mutex<S>>,
+    inner: S,
+    /// Held while a plan's steps run, so the next event waits for them. It is
+    /// let go while a `WaitTime` elapses, see `Runner::defer`.
+    slot: CallSlot,
     strategy: ST,
-    state_machine: SharedStateMachine,
+    car: CarHandle,
+    config: ControllerConfig,
+    metrics: Arc<Metrics>,
+    decisions: DecisionLog,
 }
 
 impl<S, ST> SchedulerService<S, ST> {
-    fn new(inner: S, strategy: ST, state_machine: SharedStateMachine) -> Self {
+    fn new(
+        inner: S,
+        strategy: ST,
+        car: CarHandle,
+        config: ControllerConfig,
+        metrics: Arc<Metrics>,
+        decisions: DecisionLog,
+    ) -> Self {
         SchedulerService {
-            inner: Arc::new(Mutex::new(inner)),
+            inner,
+            slot: CallSlot::new(),
             strategy,
-            state_machine,
+            car,
+            config,
+            metrics,
+            decisions,
         }
     }
 }
 
 pub struct SchedulerEventLayer<ST> {
     strategy: ST,
-    state_machine: SharedStateMachine,
+    car: CarHandle,
+    config: ControllerConfig,
+    metrics: Arc<Metrics>,
+    decisions: DecisionLog,
 }
 
 impl<ST> SchedulerEventLayer<ST> {
-    pub fn new(strategy: ST, state_machine: SharedStateMachine) -> Self {
+    pub fn new(
+        strategy: ST,
+        car: CarHandle,
+        config: ControllerConfig,
+        metrics: Arc<Metrics>,
+    ) -> Self {
         Self {
             strategy,
-            state_machine,
+            car,
+            config,
+            metrics,
+            decisions: DecisionLog::default(),
         }
     }
+
+    /// The decisions every service built by this layer records.
+    pub fn decisions(&self) -> DecisionLog {
+        self.decisions.clone()
+    }
 }
 
 impl<S, ST> Layer<S> for SchedulerEventLayer<ST>
@@ -45,47 +91,275 @@ where
 {
     type Service = SchedulerService<S, ST>;
     fn layer(&self, inner: S) -> Self::Service {
-        SchedulerService::new(inner, self.strategy.clone(), self.state_machine.clone())
+        SchedulerService::new(
+            inner,
+            self.strategy.clone(),
+            self.car.clone(),
+            self.config,
+            self.metrics.clone(),
+            self.decisions.clone(),
+        )
     }
 }
 
 impl<S, ST> Service<Event> for SchedulerService<S, ST>
 where
-    S: Service<Action, Response = (), Error = anyhow::Error> + Send + 'static,
+    S: Service<Action, Response = (), Error = Error> + Clone + Send + 'static,
     S::Future: Send + 'static,
-    ST: Clone + Strategy<Event, ScheduleEvent, SharedStateMachine> + Send + 'static,
+    ST: Clone + Strategy<Event, ScheduleEvent, CarHandle> + Send + 'static,
 {
     type Response = ();
-    type Error = anyhow::Error;
+    type Error = Error;
     type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
 
-    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        Poll::Ready(Ok(()))
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.slot.poll_acquire(cx));
+        self.inner.poll_ready(cx)
     }
 
     fn call(&mut self, event: Event) -> Self::Future {
-        let inner = self.inner.clone();
-        let strategy = self.strategy.clone();
-        let sm = self.state_machine.clone();
+        let slot = self.slot.take();
+        let mut runner = Runner {
+            inner: take_ready(&mut self.inner),
+            slot: self.slot.clone(),
+            strategy: self.strategy.clone(),
+            car: self.car.clone(),
+            config: self.config,
+            metrics: self.metrics.clone(),
+        };
+        let decisions = self.decisions.clone();
 
         Box::pin(async move {
-            let maybe_sched_events = strategy.handle(event, &sm).await;
-            if let Some(mut schedule_event) = maybe_sched_events {
-                while let Some(event) = schedule_event.pop_front() {
-                    match event {
-                        ScheduleEvent::Instant(action) => {
-                            inner.lock().await.call(action).await?;
-                        }
-                        ScheduleEvent::WaitTime(duration, action) => {
-                            tokio::time::sleep(duration).await;
-                            inner.lock().await.call(action).await?;
-                        }
-                    }
-                }
-            } else {
+            let _slot = slot;
+            let handled = runner.strategy.handle(event, &runner.car).await;
+            if let Some(decision) = handled.decision {
+                println!("Decision: {decision}");
+                decisions.record(decision);
+            }
+            let Some(plan) = handled.plan else {
                 println!("No action generated");
+                return Ok(());
+            };
+            runner.carry_out(plan, false).await
+        })
+    }
+}
+
+/// Carries out plans for the scheduler, including the steps it defers.
+#[derive(Clone)]
+struct Runner<S, ST> {
+    inner: S,
+    slot: CallSlot,
+    strategy: ST,
+    car: CarHandle,
+    config: ControllerConfig,
+    metrics: Arc<Metrics>,
+}
+
+impl<S, ST> Runner<S, ST>
+where
+    S: Service<Action, Response = (), Error = Error> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ST: Clone + Strategy<Event, ScheduleEvent, CarHandle> + Send + 'static,
+{
+    /// Carries out `plan`, applying the fault policy if a step fails. The
+    /// steps from the first `WaitTime` on are deferred; `waited` tells that
+    /// the wait heading `plan` is over.
+    async fn carry_out(&mut self, plan: Plan<ScheduleEvent>, waited: bool) -> Result<(), Error> {
+        let (car, config) = (self.car.clone(), self.config);
+        let e = match run_plan(&mut self.inner, &car, plan, waited, config, &self.metrics).await {
+            Ok(rest) => {
+                self.defer(rest);
+                return Ok(());
+            }
+            Err(e) => e,
+        };
+        if e.class() == ErrorClass::Fault
+            && handle_fault(&mut self.inner, &car, config.fault_policy, &e).await
+            && let Some(plan) = self.strategy.handle(Event::Restored, &car).await.plan
+        {
+            // The calls kept through the reset wait for no new event.
+            match run_plan(&mut self.inner, &car, plan, false, config, &self.metrics).await {
+                Ok(rest) => self.defer(rest),
+                Err(e) => eprintln!("Resuming after the reset failed: {e}"),
+            }
+        }
+        Err(e)
+    }
+
+    /// Waits out the `WaitTime` heading `rest` off the event path, so that
+    /// events arriving meanwhile are handled and can make its steps stale.
+    /// The steps then take the slot like an event would.
+    fn defer(&self, rest: Option<Plan<ScheduleEvent>>) {
+        let Some(rest) = rest else {
+            return;
+        };
+        let Some(&ScheduleEvent::WaitTime(duration, _)) = rest.steps().next().map(|s| &s.event)
+        else {
+            return;
+        };
+        let mut runner = self.clone();
+        let deferred: Pin<Box<dyn Future<Output = ()> + Send>> = Box::pin(async move {
+            tokio::time::sleep(duration).await;
+            let _slot = runner.slot.acquire().await;
+            if let Err(e) = runner.carry_out(rest, true).await {
+                eprintln!("Delayed steps failed: {e}");
+            }
+        });
+        tokio::spawn(deferred);
+    }
+}
+
+/// Runs the steps of `plan` in order until one fails, reporting how each
+/// went. Stops at a `WaitTime` that is still to be waited for, returning it
+/// with the steps after it.
+async fn run_plan<S>(
+    inner: &mut S,
+    car: &CarHandle,
+    mut plan: Plan<ScheduleEvent>,
+    mut waited: bool,
+    config: ControllerConfig,
+    metrics: &Metrics,
+) -> Result<Option<Plan<ScheduleEvent>>, Error>
+where
+    S: Service<Action, Response = (), Error = Error>,
+{
+    let mut report = PlanReport::default();
+    let mut failure = None;
+    while let Some(step) = plan.next_step() {
+        if failure.is_some() {
+            release(car, &step).await;
+            report.record(step.event, StepOutcome::Abandoned);
+            continue;
+        }
+        let due = std::mem::take(&mut waited);
+        if matches!(step.event, ScheduleEvent::WaitTime(..)) && !due {
+            plan.push_front(step);
+            break;
+        }
+        match run_step(inner, car, &step, config, metrics).await {
+            Ok(outcome) => report.record(step.event, outcome),
+            Err(e) => {
+                report.record(step.event, StepOutcome::Failed);
+                failure = Some(e);
+            }
+        }
+    }
+    for (_, outcome) in &report.steps {
+        metrics.record_step(outcome);
+    }
+    println!("{report}");
+    match failure {
+        Some(e) => Err(e),
+        None => Ok((!plan.is_empty()).then_some(plan)),
+    }
+}
+
+/// Runs one step, unless a newer event has made it stale by the time it is
+/// due.
+async fn run_step<S>(
+    inner: &mut S,
+    car: &CarHandle,
+    step: &PlanStep<ScheduleEvent>,
+    config: ControllerConfig,
+    metrics: &Metrics,
+) -> Result<StepOutcome, Error>
+where
+    S: Service<Action, Response = (), Error = Error>,
+{
+    if !step.requires.is_empty() {
+        let snapshot = car.snapshot().await?;
+        if let Some(precondition) = step.violated(snapshot.state, &snapshot.context) {
+            release(car, step).await;
+            return Ok(StepOutcome::Skipped(precondition));
+        }
+    }
+    match &step.event {
+        ScheduleEvent::Instant(action) | ScheduleEvent::WaitTime(_, action) => {
+            dispatch(inner, *action).await?
+        }
+        ScheduleEvent::Anomaly(anomaly) => {
+            metrics.record_anomaly(anomaly);
+            if config.strict {
+                metrics.record_fault();
+                return Err(Error::Anomaly(anomaly.clone()));
+            }
+        }
+    }
+    Ok(StepOutcome::Executed)
+}
+
+/// Puts the target of a step that will not run back in its queue.
+async fn release(car: &CarHandle, step: &PlanStep<ScheduleEvent>) {
+    if let Some(target) = step.target
+        && let Err(e) = car.update(move |ctx, _| ctx.release_target(target)).await
+    {
+        eprintln!("Cannot release target {target}: {e}");
+    }
+}
+
+/// Calls `inner` with `action`, retrying transient failures with a linear
+/// backoff. One that keeps failing becomes a fault.
+async fn dispatch<S>(inner: &mut S, action: Action) -> Result<(), Error>
+where
+    S: Service<Action, Response = (), Error = Error>,
+{
+    let mut attempt = 0;
+    loop {
+        let result = match inner.ready().await {
+            Ok(inner) => inner.call(action).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Err(e) if e.class() == ErrorClass::Retry && attempt < MAX_RETRIES => {
+                attempt += 1;
+                eprintln!("Retrying {action:?} after {e} (attempt {attempt}/{MAX_RETRIES})");
+                tokio::time::sleep(RETRY_BACKOFF * attempt).await;
+            }
+            Err(e) if e.class() == ErrorClass::Retry => {
+                return Err(Error::GaveUp {
+                    attempts: attempt + 1,
+                    last: Box::new(e),
+                });
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Applies the fault policy. Neither stopping nor resetting drops a call:
+/// the car owes them to the people waiting, whatever went wrong. Returns
+/// whether a reset put the calls back, so the car can set off for them.
+async fn handle_fault<S>(inner: &mut S, car: &CarHandle, policy: FaultPolicy, fault: &Error) -> bool
+where
+    S: Service<Action, Response = (), Error = Error>,
+{
+    let action = match policy {
+        FaultPolicy::Alert => {
+            eprintln!("ALERT: {fault}");
+            return false;
+        }
+        FaultPolicy::StopSafely => Action::EmergencyStop,
+        FaultPolicy::Reset => Action::Reset,
+    };
+    eprintln!("Fault policy {policy:?} after {fault}");
+    let pending = car.update(|ctx, _| ctx.pending()).await.unwrap_or_default();
+    if let Err(e) = dispatch(inner, action).await {
+        eprintln!("Fault policy {policy:?} failed: {e}");
+        return false;
+    }
+    if action != Action::Reset {
+        return false;
+    }
+    let requeued = car
+        .update(move |ctx, _| {
+            for (floor, call) in pending {
+                ctx.enqueue_call(floor, call);
             }
-            Ok(())
         })
+        .await;
+    if let Err(e) = &requeued {
+        eprintln!("Calls lost in the reset: {e}");
     }
+    requeued.is_ok()
 }
@@ -1,61 +1,149 @@
+use futures::ready;
+use std::ops::RangeInclusive;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::Poll;
-use tokio::sync::Mutex;
-use tower::{Layer, Service};
+use tower::{Layer, Service, ServiceExt};
 
+use crate::error::Error;
+use crate::metrics::Metrics;
+use crate::services::readiness::{CallSlot, take_ready};
 use crate::types::event::Event;
 
+#[derive(Clone)]
 pub struct UdpEventService<S> {
-    inner: Arc<Mutex<S>>,
+    inner: S,
+    /// Shared by network and injected events, which go through one at a time.
+    slot: CallSlot,
+    metrics: Arc<Metrics>,
+    /// The building's floors; events naming any other are invalid packets.
+    floors: RangeInclusive<u8>,
 }
 
 impl<S> UdpEventService<S> {
-    fn new(inner: S) -> Self {
+    fn new(inner: S, metrics: Arc<Metrics>, floors: RangeInclusive<u8>) -> Self {
         UdpEventService {
-            inner: Arc::new(Mutex::new(inner)),
+            inner,
+            slot: CallSlot::new(),
+            metrics,
+            floors,
         }
     }
+
+    /// Parses a datagram, rejecting events for floors the building lacks.
+    fn parse(&self, raw: &[u8]) -> Vec<Result<Event, Error>> {
+        let floors = &self.floors;
+        Event::parse_datagram(raw)
+            .into_iter()
+            .map(|parsed| {
+                let event = parsed?;
+                match event.floor() {
+                    Some(floor) if !floors.contains(&floor) => Err(Error::InvalidPacket(format!(
+                        "floor {floor} outside the building in {event:?}"
+                    ))),
+                    _ => Ok(event),
+                }
+            })
+            .collect()
+    }
 }
 
 impl<S> Service<&[u8]> for UdpEventService<S>
 where
-    S: Service<Event, Response = (), Error = anyhow::Error> + Send + 'static,
+    S: Service<Event, Response = (), Error = Error> + Clone + Send + 'static,
     S::Future: Send + 'static,
 {
     type Response = ();
-    type Error = anyhow::Error;
+    type Error = Error;
     type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
 
-    fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
-        Poll::Ready(Ok(()))
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.slot.poll_acquire(cx));
+        self.inner.poll_ready(cx)
     }
 
     fn call(&mut self, raw: &[u8]) -> Self::Future {
-        let maybe_event = Event::try_from(raw);
-        let inner = self.inner.clone();
+        let slot = self.slot.take();
+        let parsed = self.parse(raw);
+        let mut inner = take_ready(&mut self.inner);
+        let metrics = self.metrics.clone();
 
         Box::pin(async move {
-            match maybe_event {
-                Ok(ev) => {
-                    println!("Event received: {ev:?}");
-                    inner.lock().await.call(ev).await
-                }
-                Err(e) => {
-                    eprintln!("Invalid packet: {e:?}");
-                    Ok(())
+            let _slot = slot;
+            // Every valid event in the datagram is delivered, even after an
+            // earlier line was invalid or failed; the most severe error is
+            // reported once all have gone through.
+            let mut failure: Option<Error> = None;
+            for maybe_event in parsed {
+                let result = match maybe_event {
+                    Ok(ev) => {
+                        println!("Event received: {ev:?}");
+                        metrics.record_event(ev.kind());
+                        match inner.ready().await {
+                            Ok(inner) => inner.call(ev).await,
+                            Err(e) => Err(e),
+                        }
+                    }
+                    Err(e) => {
+                        metrics.record_invalid_packet();
+                        Err(e)
+                    }
+                };
+                if let Err(e) = result
+                    && failure.as_ref().is_none_or(|f| e.class() > f.class())
+                {
+                    failure = Some(e);
                 }
             }
+            failure.map_or(Ok(()), Err)
+        })
+    }
+}
+
+/// Locally injected events (e.g. from the operator console) skip parsing but
+/// otherwise take the same path as network events.
+impl<S> Service<Event> for UdpEventService<S>
+where
+    S: Service<Event, Response = (), Error = Error> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.slot.poll_acquire(cx));
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, ev: Event) -> Self::Future {
+        let slot = self.slot.take();
+        let mut inner = take_ready(&mut self.inner);
+        self.metrics.record_event(ev.kind());
+
+        Box::pin(async move {
+            let _slot = slot;
+            println!("Event injected: {ev:?}");
+            inner.ready().await?.call(ev).await
         })
     }
 }
 
-pub struct UdpEventLayer;
+pub struct UdpEventLayer {
+    metrics: Arc<Metrics>,
+    floors: RangeInclusive<u8>,
+}
+
+impl UdpEventLayer {
+    pub fn new(metrics: Arc<Metrics>, floors: RangeInclusive<u8>) -> Self {
+        Self { metrics, floors }
+    }
+}
 
 impl<S> Layer<S> for UdpEventLayer {
     type Service = UdpEventService<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        UdpEventService::new(inner)
+        UdpEventService::new(inner, self.metrics.clone(), self.floors.clone())
     }
 }
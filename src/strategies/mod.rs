@@ -1 +1,2 @@
 pub mod scan;
+pub mod switchable;
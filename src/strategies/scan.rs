@@ -1,103 +1,240 @@
-use crate::context::{ElevatorContext, Location};
-use crate::strategy::Strategy;
-use crate::transition::{SharedStateMachine, State};
+use crate::car::CarHandle;
+use crate::context::{Call, CarContext, ElevatorContext, Location};
+use crate::decision::{Decision, QueueSnapshot, Reason};
+use crate::error::Anomaly;
+use crate::strategy::{Handled, Strategy};
+use crate::transition::State;
 use crate::types::event::Event;
+use crate::types::plan::{Plan, PlanStep, Precondition};
 use crate::types::sched_events::{Action, ScheduleEvent};
 use async_trait::async_trait;
-use std::collections::VecDeque;
-use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Mutex;
 
-#[derive(Debug, Clone)]
-pub struct ScanStrategy {
-    elevator_context: Arc<Mutex<ElevatorContext>>,
-}
+#[derive(Debug, Clone, Default)]
+pub struct ScanStrategy;
 
 impl ScanStrategy {
-    pub fn new(elevator_data: Arc<Mutex<ElevatorContext>>) -> Self {
-        Self {
-            elevator_context: elevator_data,
-        }
+    pub fn new() -> Self {
+        Self
     }
-}
 
-#[async_trait]
-impl Strategy<Event, ScheduleEvent, SharedStateMachine> for ScanStrategy {
-    async fn handle(
-        &self,
+    /// Updates the context for `event` and plans the car's next actions.
+    pub fn plan(
         event: Event,
-        state_machine: &SharedStateMachine,
-    ) -> Option<VecDeque<ScheduleEvent>> {
-        let mut elevator_context = self.elevator_context.lock().await;
-        let state = state_machine
-            .lock()
-            .await
-            .as_ref()
-            .expect("state machine should not be None")
-            .state();
-        let mut sched_events = VecDeque::new();
+        elevator_context: &mut ElevatorContext,
+        state: State,
+    ) -> Handled<ScheduleEvent> {
+        let mut sched_events = Plan::new();
+        let mut homed = false;
         match event {
-            Event::PanelButtonPressed(floor)
-            | Event::ElevatorUp(floor)
-            | Event::ElevatorDown(floor) => {
-                elevator_context.enqueue_request(floor);
+            Event::PanelButtonPressed(floor) => {
+                Self::call(elevator_context, state, floor, Call::Car)
+            }
+            Event::ElevatorUp(floor) => Self::call(elevator_context, state, floor, Call::HallUp),
+            Event::ElevatorDown(floor) => {
+                Self::call(elevator_context, state, floor, Call::HallDown)
             }
             Event::DoorOpened(floor) => {
+                elevator_context.stopped_at(floor);
                 if elevator_context.active_target == Some(floor) && state == State::DoorOpening {
-                    sched_events.push_back(ScheduleEvent::Instant(Action::DoorOpened));
-                    sched_events.push_back(ScheduleEvent::WaitTime(
-                        Duration::from_secs(2),
-                        Action::ClosingDoor,
-                    ));
+                    elevator_context.serve(floor);
+                    sched_events.then(ScheduleEvent::Instant(Action::DoorOpened));
+                    if elevator_context.overloaded() {
+                        sched_events.then(ScheduleEvent::Instant(Action::HoldingDoor));
+                    }
+                    Self::close_door_later(&mut sched_events);
                 } else {
-                    eprintln!("elevator behaving strange, door opened on unexpected floor: {floor}")
+                    eprintln!(
+                        "elevator behaving strange, door opened on unexpected floor: {floor}"
+                    );
+                    sched_events.then(ScheduleEvent::Anomaly(Anomaly::UnexpectedDoorOpened(floor)));
+                    // No new target after an anomaly: in strict mode it fails
+                    // the plan, and the call taken for the target would be lost.
+                    return Some(sched_events).into();
                 }
             }
             Event::DoorClosed(floor) => {
+                elevator_context.stopped_at(floor);
                 if state == State::DoorClosing {
-                    sched_events.push_back(ScheduleEvent::Instant(Action::DoorClosed))
+                    sched_events.then(ScheduleEvent::Instant(Action::DoorClosed));
                 } else {
-                    eprintln!("elevator behaving strange, door closed on unexpected floor: {floor}")
+                    eprintln!(
+                        "elevator behaving strange, door closed on unexpected floor: {floor}"
+                    );
+                    sched_events.then(ScheduleEvent::Anomaly(Anomaly::UnexpectedDoorClosed(floor)));
+                    return Some(sched_events).into();
                 }
             }
             Event::ElevatorStopped(floor) => {
+                elevator_context.stopped_at(floor);
                 if elevator_context.active_target == Some(floor) && state == State::Braking {
-                    sched_events.push_back(ScheduleEvent::Instant(Action::Stopped));
-                    sched_events.push_back(ScheduleEvent::Instant(Action::OpeningDoor))
+                    sched_events
+                        .then(ScheduleEvent::Instant(Action::Stopped))
+                        .then_if(
+                            ScheduleEvent::Instant(Action::OpeningDoor),
+                            [
+                                Precondition::InState(State::Idle),
+                                Precondition::AtFloor(floor),
+                            ],
+                        );
+                } else if state == State::EmergencyBrake {
+                    sched_events.then(ScheduleEvent::Instant(Action::Stopped));
+                } else if state == State::Braking && elevator_context.active_target.is_none() {
+                    // Only homing brakes without a target. Now the car knows
+                    // its floor, the calls held meanwhile can be queued.
+                    sched_events.then(ScheduleEvent::Instant(Action::Stopped));
+                    elevator_context.queue_held_calls();
+                    homed = true;
                 } else {
                     eprintln!(
                         "elevator behaving strange, door stopped on unexpected floor: {floor}"
-                    )
+                    );
+                    sched_events.then(ScheduleEvent::Anomaly(Anomaly::UnexpectedStop(floor)));
+                    return Some(sched_events).into();
                 }
             }
             Event::ElevatorApproaching(floor) => {
-                elevator_context.approach_floor(floor);
-                if elevator_context.active_target == Some(floor)
-                    && (state == State::MovingUp || state == State::MovingDown)
-                {
-                    sched_events.push_back(ScheduleEvent::Instant(Action::Braking))
+                elevator_context.approach(floor);
+                if state == State::Homing {
+                    // The first floor the recall approaches tells where the car is.
+                    println!("Homing found floor {floor}");
+                    sched_events.then(ScheduleEvent::Instant(Action::Braking));
+                    return Some(sched_events).into();
+                }
+                let moving = matches!(state, State::MovingUp | State::MovingDown);
+                if moving && elevator_context.bypass(floor) {
+                    println!("Near full, passing floor {floor} for its hall calls");
+                }
+                let queues = QueueSnapshot::of(elevator_context);
+                if moving && elevator_context.stop_on_the_way(floor) {
+                    println!("Stopping at floor {floor} on the way");
+                    let decision = Decision {
+                        event: event.clone(),
+                        state,
+                        location: elevator_context.location(),
+                        direction_up: elevator_context.direction_up,
+                        chosen: floor,
+                        reason: Reason::OnTheWay,
+                        queues,
+                    };
+                    sched_events.then(ScheduleEvent::Instant(Action::Braking));
+                    return Handled::from(Some(sched_events)).explained(Some(decision));
+                }
+                if moving && elevator_context.active_target == Some(floor) {
+                    sched_events.then(ScheduleEvent::Instant(Action::Braking));
                 } else {
                     println!("elevator approaching floor: {floor}")
                 }
             }
-            Event::KeySwitched(_floor) => {}
+            Event::KeySwitched(key) => {
+                elevator_context.key = key;
+            }
+            Event::LoadWeighed(load) => {
+                let was_overloaded = elevator_context.overloaded();
+                elevator_context.load = load;
+                if elevator_context.overloaded() {
+                    println!("Overload buzzer: car at {load}% of capacity");
+                    if state == State::DoorOpened {
+                        sched_events.then(ScheduleEvent::Instant(Action::HoldingDoor));
+                    }
+                } else if was_overloaded && state == State::DoorOpened {
+                    // The close skipped while overloaded is due again.
+                    Self::close_door_later(&mut sched_events);
+                }
+            }
+            Event::CallCancelled(floor) => {
+                if !elevator_context.cancel_request(floor) {
+                    println!("no pending call to cancel for floor {floor}");
+                }
+            }
+            Event::EmergencyStop => {
+                elevator_context.clear_requests();
+                sched_events.then(ScheduleEvent::Instant(Action::EmergencyStop));
+                return Some(sched_events).into();
+            }
+            Event::Reset => {
+                sched_events.then(ScheduleEvent::Instant(Action::Reset));
+                return Some(sched_events).into();
+            }
+            Event::StrategySwitched(_) | Event::Restored => {}
         }
 
         println!("{:?} with state {:?}", elevator_context, state);
 
-        if state == State::Idle || matches!(event, Event::DoorClosed(_)) {
-            if let Some(target) = elevator_context.next_target() {
-                if Location::AtFloor(target) > elevator_context.current_location {
-                    sched_events.push_back(ScheduleEvent::Instant(Action::MovingUp));
-                } else if Location::AtFloor(target) < elevator_context.current_location {
-                    sched_events.push_back(ScheduleEvent::Instant(Action::MovingDown));
-                } else {
-                    println!("elevator already on floor {target}");
-                }
-            }
+        let mut decision = None;
+        let queues = QueueSnapshot::of(elevator_context);
+        let direction_up = elevator_context.direction_up;
+        if (state == State::Idle || matches!(event, Event::DoorClosed(_)) || homed)
+            && state != State::EmergencyBrake
+            && let Some((target, reason)) = elevator_context.choose_target()
+        {
+            decision = Some(Decision {
+                event: event.clone(),
+                state,
+                location: elevator_context.location(),
+                direction_up,
+                chosen: target,
+                reason,
+                queues,
+            });
+            let location = elevator_context.location();
+            let action = if Location::AtFloor(target) > location {
+                Action::MovingUp
+            } else if Location::AtFloor(target) < location {
+                Action::MovingDown
+            } else {
+                Action::OpeningDoor
+            };
+            // After a door cycle the car only leaves once the door did close.
+            sched_events.then_toward(
+                target,
+                ScheduleEvent::Instant(action),
+                [Precondition::InState(State::Idle)],
+            );
+        }
+
+        if !elevator_context.lamps_to_clear.is_empty() {
+            sched_events.push_front(PlanStep {
+                event: ScheduleEvent::Instant(Action::ClearingLamps),
+                requires: Vec::new(),
+                target: None,
+            });
+        }
+
+        Handled::from((!sched_events.is_empty()).then_some(sched_events)).explained(decision)
+    }
+
+    /// Closes the door after a while, unless the car is over capacity by
+    /// then; it stays open until someone steps off.
+    fn close_door_later(sched_events: &mut Plan<ScheduleEvent>) {
+        sched_events.then_if(
+            ScheduleEvent::WaitTime(Duration::from_secs(2), Action::ClosingDoor),
+            [
+                Precondition::InState(State::DoorOpened),
+                Precondition::WithinCapacity,
+            ],
+        );
+    }
+
+    /// A homing car does not know where it is, so its calls are held until
+    /// it does.
+    fn call(elevator_context: &mut ElevatorContext, state: State, floor: u8, call: Call) {
+        if state == State::Homing {
+            elevator_context.hold_call(floor, call);
+        } else {
+            elevator_context.enqueue_call(floor, call);
         }
+    }
+}
 
-        (!sched_events.is_empty()).then_some(sched_events)
+#[async_trait]
+impl Strategy<Event, ScheduleEvent, CarHandle> for ScanStrategy {
+    async fn handle(&self, event: Event, car: &CarHandle) -> Handled<ScheduleEvent> {
+        car.update(move |ctx, state| Self::plan(event, ctx, state))
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Cannot plan: {e}");
+                Handled::nothing()
+            })
     }
 }
@@ -0,0 +1,58 @@
+use crate::car::CarHandle;
+use crate::strategy::{Handled, Strategy};
+use crate::types::event::Event;
+use crate::types::sched_events::ScheduleEvent;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+pub type ElevatorStrategy = dyn Strategy<Event, ScheduleEvent, CarHandle>;
+
+/// Delegates to one of several registered strategies, swapped at runtime by
+/// `Event::StrategySwitched`.
+#[derive(Clone)]
+pub struct SwitchableStrategy {
+    registry: Arc<HashMap<String, Arc<ElevatorStrategy>>>,
+    active: Arc<RwLock<(String, Arc<ElevatorStrategy>)>>,
+}
+
+impl SwitchableStrategy {
+    pub fn new(registry: HashMap<String, Arc<ElevatorStrategy>>, initial: &str) -> Self {
+        let strategy = registry
+            .get(initial)
+            .unwrap_or_else(|| panic!("strategy {initial} is not registered"))
+            .clone();
+        Self {
+            registry: Arc::new(registry),
+            active: Arc::new(RwLock::new((initial.to_string(), strategy))),
+        }
+    }
+
+    pub fn active_name(&self) -> String {
+        self.active.read().unwrap().0.clone()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self.registry.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+#[async_trait]
+impl Strategy<Event, ScheduleEvent, CarHandle> for SwitchableStrategy {
+    async fn handle(&self, event: Event, car: &CarHandle) -> Handled<ScheduleEvent> {
+        if let Event::StrategySwitched(name) = &event {
+            match self.registry.get(name) {
+                Some(strategy) => {
+                    println!("Switching strategy to {name}");
+                    *self.active.write().unwrap() = (name.clone(), strategy.clone());
+                }
+                None => eprintln!("Unknown strategy {name}, options: {:?}", self.names()),
+            }
+            return Handled::nothing();
+        }
+        let strategy = self.active.read().unwrap().1.clone();
+        strategy.handle(event, car).await
+    }
+}
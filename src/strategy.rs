@@ -1,11 +1,39 @@
+use crate::decision::Decision;
+use crate::types::plan::Plan;
 use async_trait::async_trait;
-use std::collections::VecDeque;
 
 #[async_trait]
 pub trait Strategy<Event, ScheduleEvent, StateMachine>: Send + Sync {
-    async fn handle(
-        &self,
-        event: Event,
-        state_machine: &StateMachine,
-    ) -> Option<VecDeque<ScheduleEvent>>;
+    async fn handle(&self, event: Event, state_machine: &StateMachine) -> Handled<ScheduleEvent>;
+}
+
+/// A strategy's answer to one event: what to do, and, when it picked a new
+/// target, why.
+#[derive(Debug, Clone)]
+pub struct Handled<ScheduleEvent> {
+    pub plan: Option<Plan<ScheduleEvent>>,
+    pub decision: Option<Decision>,
+}
+
+impl<ScheduleEvent> Handled<ScheduleEvent> {
+    pub fn nothing() -> Self {
+        Handled {
+            plan: None,
+            decision: None,
+        }
+    }
+
+    pub fn explained(mut self, decision: Option<Decision>) -> Self {
+        self.decision = decision;
+        self
+    }
+}
+
+impl<ScheduleEvent> From<Option<Plan<ScheduleEvent>>> for Handled<ScheduleEvent> {
+    fn from(plan: Option<Plan<ScheduleEvent>>) -> Self {
+        Handled {
+            plan,
+            decision: None,
+        }
+    }
 }
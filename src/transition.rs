@@ -1,55 +1,64 @@
-use crate::context::ElevatorContext;
-use crate::types::cmd::Command;
+use crate::command_channel::CommandSender;
+use crate::context::{Call, CarContext, ElevatorContext, Location};
+use crate::error::Error;
+use crate::types::cmd::{Command, CommandBatch};
 use crate::types::sched_events::Action;
 use async_trait::async_trait;
 use std::fmt::Debug;
 use std::marker::PhantomData;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::ops::RangeInclusive;
 
-pub type SharedStateMachine = Arc<Mutex<Option<BoxedTransition>>>;
+pub type BoxedTransition<C = ElevatorContext> = Box<dyn Transition<C> + Sync + Send + 'static>;
 
-pub type BoxedTransition = Box<dyn Transition<ElevatorContext> + Sync + Send + 'static>;
 #[async_trait]
 pub trait Transition<C>: Send + 'static + Sync + Debug
 where
     C: Debug + Send + Sync + 'static,
 {
-    async fn on_event(
-        self: Box<Self>,
-        action: Action,
-        ctx: &mut C,
-    ) -> anyhow::Result<Box<dyn Transition<C> + Sync + Send + 'static>>;
+    async fn on_event(self: Box<Self>, action: Action, ctx: &mut C) -> TransitionResult<C>;
 
     fn state(&self) -> State;
 }
 
-pub trait IntoBoxedTransition {
-    fn boxed(self) -> BoxedTransition;
+pub type TransitionResult<C> =
+    Result<Box<dyn Transition<C> + Sync + Send + 'static>, TransitionError<C>>;
+
+/// A failed transition hands the machine back, so a failure never loses track
+/// of the state the car is in.
+#[derive(Debug)]
+pub struct TransitionError<C> {
+    pub machine: Box<dyn Transition<C> + Sync + Send + 'static>,
+    pub error: Error,
+}
+
+pub trait IntoBoxedTransition<C> {
+    fn boxed(self) -> BoxedTransition<C>;
 }
 
-impl<T> IntoBoxedTransition for T
+impl<T, C> IntoBoxedTransition<C> for T
 where
-    T: Transition<ElevatorContext> + Send + Sync + 'static,
+    T: Transition<C> + Send + Sync + 'static,
+    C: Debug + Send + Sync + 'static,
 {
-    fn boxed(self) -> BoxedTransition {
+    fn boxed(self) -> BoxedTransition<C> {
         Box::new(self)
     }
 }
 
 #[derive(Debug)]
 pub struct ElevatorState<State> {
-    tx: tokio::sync::mpsc::UnboundedSender<Command>,
+    tx: CommandSender,
     _marker: PhantomData<State>,
 }
 
 impl<State> ElevatorState<State> {
-    async fn send_command(&self, command: Command) -> anyhow::Result<()> {
-        self.tx.send(command)?;
-        Ok(())
+    /// Resolves once the batch has been written, so callers only move to the
+    /// next state after the hardware was actually told.
+    async fn send_commands(&self, batch: CommandBatch) -> crate::error::Result<()> {
+        self.tx.send(batch).await
     }
 
-    pub fn new(tx: tokio::sync::mpsc::UnboundedSender<Command>) -> ElevatorState<State> {
+    pub fn new(tx: CommandSender) -> ElevatorState<State> {
         ElevatorState::<State> {
             tx,
             _marker: PhantomData,
@@ -64,6 +73,66 @@ impl<State> ElevatorState<State> {
     }
 }
 
+impl<S> ElevatorState<S> {
+    async fn command<C>(self: Box<Self>, command: Command) -> Result<Box<Self>, TransitionError<C>>
+    where
+        Self: Transition<C>,
+        C: CarContext,
+    {
+        self.commands(vec![command]).await
+    }
+
+    async fn commands<C>(
+        self: Box<Self>,
+        batch: CommandBatch,
+    ) -> Result<Box<Self>, TransitionError<C>>
+    where
+        Self: Transition<C>,
+        C: CarContext,
+    {
+        match self.send_commands(batch).await {
+            Ok(()) => Ok(self),
+            Err(error) => Err(TransitionError {
+                machine: self,
+                error,
+            }),
+        }
+    }
+
+    async fn reset<C>(self: Box<Self>, ctx: &mut C) -> TransitionResult<C>
+    where
+        Self: Transition<C>,
+        C: CarContext,
+    {
+        println!("Resetting.");
+        let this = self.command(Command::R).await?;
+        ctx.reset();
+        Ok(this.transit::<Idle>().boxed())
+    }
+
+    /// Clears the lamps of the calls the context dropped, in any state. They
+    /// are only forgotten once sent, so a failed send leaves them to retry.
+    async fn clear_lamps<C>(self: Box<Self>, ctx: &mut C) -> TransitionResult<C>
+    where
+        Self: Transition<C>,
+        C: CarContext,
+    {
+        let batch = lamps_off(&ctx.lamps_to_clear(), ctx.floors());
+        let this = if batch.is_empty() {
+            self
+        } else {
+            self.commands(batch).await?
+        };
+        ctx.take_lamps_to_clear();
+        Ok(this)
+    }
+
+    fn halt<C: CarContext>(self) -> BoxedTransition<C> {
+        println!("Emergency stop, holding until reset.");
+        self.transit::<EmergencyBrake>().boxed()
+    }
+}
+
 #[derive(Debug)]
 pub struct PreStart;
 #[derive(Debug)]
@@ -84,8 +153,11 @@ pub struct DoorClosed;
 pub struct Braking;
 #[derive(Debug)]
 pub struct EmergencyBrake;
+/// Recalling a car whose position is unknown until it approaches a floor.
+#[derive(Debug)]
+pub struct Homing;
 
-#[derive(Debug, Clone, PartialOrd, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Eq)]
 pub enum State {
     Idle,
     MovingUp,
@@ -94,39 +166,122 @@ pub enum State {
     DoorClosing,
     DoorOpening,
     Braking,
+    EmergencyBrake,
+    Homing,
+}
+
+impl State {
+    pub const ALL: [State; 9] = [
+        State::Idle,
+        State::MovingUp,
+        State::MovingDown,
+        State::DoorOpened,
+        State::DoorClosing,
+        State::DoorOpening,
+        State::Braking,
+        State::EmergencyBrake,
+        State::Homing,
+    ];
+
+    /// Builds the typestate for `self`, e.g. to resume a machine in a known state.
+    pub fn enter<C: CarContext>(self, tx: CommandSender) -> BoxedTransition<C> {
+        match self {
+            State::Idle => ElevatorState::<Idle>::new(tx).boxed(),
+            State::MovingUp => ElevatorState::<MovingUp>::new(tx).boxed(),
+            State::MovingDown => ElevatorState::<MovingDown>::new(tx).boxed(),
+            State::DoorOpened => ElevatorState::<DoorOpened>::new(tx).boxed(),
+            State::DoorClosing => ElevatorState::<DoorClosing>::new(tx).boxed(),
+            State::DoorOpening => ElevatorState::<DoorOpening>::new(tx).boxed(),
+            State::Braking => ElevatorState::<Braking>::new(tx).boxed(),
+            State::EmergencyBrake => ElevatorState::<EmergencyBrake>::new(tx).boxed(),
+            State::Homing => ElevatorState::<Homing>::new(tx).boxed(),
+        }
+    }
+}
+
+/// The commands clearing the lamps of `calls`. Lifty has no up button on
+/// the top floor nor down button on the bottom one.
+fn lamps_off(calls: &[(u8, Call)], floors: RangeInclusive<u8>) -> CommandBatch {
+    calls
+        .iter()
+        .filter_map(|&(floor, call)| match call {
+            Call::Car => Some(Command::CP(floor)),
+            Call::HallUp => (floor < *floors.end()).then_some(Command::CU(floor)),
+            Call::HallDown => (floor > *floors.start()).then_some(Command::CD(floor)),
+        })
+        .collect()
 }
 
 impl ElevatorState<PreStart> {
-    pub async fn init(self) -> anyhow::Result<ElevatorState<Idle>> {
-        self.send_command(Command::R).await?;
+    pub async fn init(self) -> crate::error::Result<ElevatorState<Idle>> {
+        self.send_commands(vec![Command::R]).await?;
         Ok(self.transit::<Idle>())
     }
+
+    /// Starts a recall move for hardware that cannot be reset to a known
+    /// floor. The car brakes for the first floor it approaches, which tells
+    /// where it is, and becomes `Idle` once stopped there. The move is blind:
+    /// a car at the end of the shaft or with its door open crashes Lifty
+    /// instead, which `car::reset_if_lost` recovers from.
+    pub async fn home(self, up: bool) -> crate::error::Result<ElevatorState<Homing>> {
+        println!("Homing {}.", if up { "up" } else { "down" });
+        let command = if up { Command::MU } else { Command::MD };
+        self.send_commands(vec![command]).await?;
+        Ok(self.transit::<Homing>())
+    }
+
+    /// Takes over a car an earlier controller left in `state`, without
+    /// resetting it. Only call this for a state the hardware is known to
+    /// still be in; an open door is closed again, since nothing is left to
+    /// time it.
+    pub async fn resume<C: CarContext>(
+        self,
+        state: State,
+    ) -> crate::error::Result<BoxedTransition<C>> {
+        match state {
+            State::DoorOpened => {
+                self.send_commands(vec![Command::DC]).await?;
+                Ok(self.transit::<DoorClosing>().boxed())
+            }
+            state => Ok(state.enter(self.tx)),
+        }
+    }
 }
 
 #[async_trait]
-impl Transition<ElevatorContext> for ElevatorState<Idle> {
-    async fn on_event(
-        self: Box<Self>,
-        action: Action,
-        ctx: &mut ElevatorContext,
-    ) -> anyhow::Result<BoxedTransition> {
+impl<C: CarContext> Transition<C> for ElevatorState<Idle> {
+    async fn on_event(self: Box<Self>, action: Action, ctx: &mut C) -> TransitionResult<C> {
         match action {
             Action::MovingUp => {
                 println!("Moving up");
-                self.send_command(Command::MU).await?;
-                ctx.transit_floor();
-                Ok(self.transit::<MovingUp>().boxed())
+                let this = self.command(Command::MU).await?;
+                ctx.depart(true);
+                Ok(this.transit::<MovingUp>().boxed())
             }
             Action::MovingDown => {
                 println!("Moving down");
-                self.send_command(Command::MD).await?;
-                ctx.transit_floor();
-                Ok(self.transit::<MovingDown>().boxed())
+                let this = self.command(Command::MD).await?;
+                ctx.depart(false);
+                Ok(this.transit::<MovingDown>().boxed())
             }
             Action::OpeningDoor => {
                 println!("Opening door");
-                self.send_command(Command::DO).await?;
-                Ok(self.transit::<DoorOpening>().boxed())
+                // The lamps of the calls the stop serves go out with the
+                // door command, in one datagram.
+                let floor = match ctx.location() {
+                    Location::AtFloor(floor) => Some(floor),
+                    Location::BetweenFloors(..) => None,
+                };
+                let mut lamps = ctx.lamps_to_clear();
+                lamps.extend(floor.map_or_else(Vec::new, |floor| ctx.served_at(floor)));
+                let mut batch = lamps_off(&lamps, ctx.floors());
+                batch.push(Command::DO);
+                let this = self.commands(batch).await?;
+                if let Some(floor) = floor {
+                    ctx.serve(floor);
+                }
+                ctx.take_lamps_to_clear();
+                Ok(this.transit::<DoorOpening>().boxed())
             }
             Action::Braking => {
                 eprintln!("Can't Brake, already Stopped.");
@@ -140,13 +295,16 @@ impl Transition<ElevatorContext> for ElevatorState<Idle> {
                 eprintln!("Door Already Closed");
                 Ok(self)
             }
-            Action::DoorOpened | Action::ClosingDoor => {
+            Action::DoorOpened | Action::ClosingDoor | Action::HoldingDoor => {
                 eprintln!(
                     "Strange door status: {:?}, state in {:?}",
                     action, self._marker
                 );
                 Ok(self)
             }
+            Action::EmergencyStop => Ok(self.halt()),
+            Action::ClearingLamps => self.clear_lamps(ctx).await,
+            Action::Reset => self.reset(ctx).await,
         }
     }
 
@@ -156,18 +314,22 @@ impl Transition<ElevatorContext> for ElevatorState<Idle> {
 }
 
 #[async_trait]
-impl Transition<ElevatorContext> for ElevatorState<MovingUp> {
-    async fn on_event(
-        self: Box<Self>,
-        action: Action,
-        _ctx: &mut ElevatorContext,
-    ) -> anyhow::Result<BoxedTransition> {
+impl<C: CarContext> Transition<C> for ElevatorState<MovingUp> {
+    async fn on_event(self: Box<Self>, action: Action, ctx: &mut C) -> TransitionResult<C> {
         match action {
             Action::Braking => {
                 println!("Braking.");
-                self.send_command(Command::S).await?;
-                Ok(self.transit::<Braking>().boxed())
+                let this = self.command(Command::S).await?;
+                ctx.level();
+                Ok(this.transit::<Braking>().boxed())
+            }
+            Action::EmergencyStop => {
+                let this = self.command(Command::S).await?;
+                ctx.level();
+                Ok(this.halt())
             }
+            Action::ClearingLamps => self.clear_lamps(ctx).await,
+            Action::Reset => self.reset(ctx).await,
             ev => {
                 eprintln!(
                     "Ignored: invalid schedule event {ev:?} in state {:?}",
@@ -184,18 +346,22 @@ impl Transition<ElevatorContext> for ElevatorState<MovingUp> {
 }
 
 #[async_trait]
-impl Transition<ElevatorContext> for ElevatorState<MovingDown> {
-    async fn on_event(
-        self: Box<Self>,
-        action: Action,
-        _ctx: &mut ElevatorContext,
-    ) -> anyhow::Result<BoxedTransition> {
+impl<C: CarContext> Transition<C> for ElevatorState<MovingDown> {
+    async fn on_event(self: Box<Self>, action: Action, ctx: &mut C) -> TransitionResult<C> {
         match action {
             Action::Braking => {
                 println!("Braking.");
-                self.send_command(Command::S).await?;
-                Ok(self.transit::<Braking>().boxed())
+                let this = self.command(Command::S).await?;
+                ctx.level();
+                Ok(this.transit::<Braking>().boxed())
+            }
+            Action::EmergencyStop => {
+                let this = self.command(Command::S).await?;
+                ctx.level();
+                Ok(this.halt())
             }
+            Action::ClearingLamps => self.clear_lamps(ctx).await,
+            Action::Reset => self.reset(ctx).await,
             ev => {
                 eprintln!(
                     "Ignored: invalid schedule event {ev:?} in state {:?}",
@@ -212,18 +378,16 @@ impl Transition<ElevatorContext> for ElevatorState<MovingDown> {
 }
 
 #[async_trait]
-impl Transition<ElevatorContext> for ElevatorState<Braking> {
-    async fn on_event(
-        self: Box<Self>,
-        action: Action,
-        ctx: &mut ElevatorContext,
-    ) -> anyhow::Result<BoxedTransition> {
+impl<C: CarContext> Transition<C> for ElevatorState<Braking> {
+    async fn on_event(self: Box<Self>, action: Action, ctx: &mut C) -> TransitionResult<C> {
         match action {
             Action::Stopped => {
                 println!("Stopped.");
-                ctx.transit_floor();
                 Ok(self.transit::<Idle>().boxed())
             }
+            Action::EmergencyStop => Ok(self.halt()),
+            Action::ClearingLamps => self.clear_lamps(ctx).await,
+            Action::Reset => self.reset(ctx).await,
             ev => {
                 eprintln!(
                     "Ignored: invalid schedule event {ev:?} in state {:?}",
@@ -239,12 +403,8 @@ impl Transition<ElevatorContext> for ElevatorState<Braking> {
 }
 
 #[async_trait]
-impl Transition<ElevatorContext> for ElevatorState<DoorOpening> {
-    async fn on_event(
-        self: Box<Self>,
-        action: Action,
-        _ctx: &mut ElevatorContext,
-    ) -> anyhow::Result<BoxedTransition> {
+impl<C: CarContext> Transition<C> for ElevatorState<DoorOpening> {
+    async fn on_event(self: Box<Self>, action: Action, ctx: &mut C) -> TransitionResult<C> {
         match action {
             Action::DoorOpened => {
                 println!("Door Opened.");
@@ -254,6 +414,9 @@ impl Transition<ElevatorContext> for ElevatorState<DoorOpening> {
                 println!("Double Opening Door.");
                 Ok(self)
             }
+            Action::EmergencyStop => Ok(self.halt()),
+            Action::ClearingLamps => self.clear_lamps(ctx).await,
+            Action::Reset => self.reset(ctx).await,
             ev => {
                 eprintln!(
                     "Ignored: invalid schedule event {ev:?} in state {:?}",
@@ -270,18 +433,21 @@ impl Transition<ElevatorContext> for ElevatorState<DoorOpening> {
 }
 
 #[async_trait]
-impl Transition<ElevatorContext> for ElevatorState<DoorOpened> {
-    async fn on_event(
-        self: Box<Self>,
-        action: Action,
-        _ctx: &mut ElevatorContext,
-    ) -> anyhow::Result<BoxedTransition> {
+impl<C: CarContext> Transition<C> for ElevatorState<DoorOpened> {
+    async fn on_event(self: Box<Self>, action: Action, ctx: &mut C) -> TransitionResult<C> {
         match action {
+            Action::HoldingDoor => {
+                eprintln!("Overload buzzer: over capacity, door held open.");
+                Ok(self)
+            }
             Action::ClosingDoor => {
                 println!("Closing Door.");
-                self.send_command(Command::DC).await?;
-                Ok(self.transit::<DoorClosing>().boxed())
+                let this = self.command(Command::DC).await?;
+                Ok(this.transit::<DoorClosing>().boxed())
             }
+            Action::EmergencyStop => Ok(self.halt()),
+            Action::ClearingLamps => self.clear_lamps(ctx).await,
+            Action::Reset => self.reset(ctx).await,
             ev => {
                 eprintln!(
                     "Ignored: invalid schedule event {ev:?} in state {:?}",
@@ -297,12 +463,8 @@ impl Transition<ElevatorContext> for ElevatorState<DoorOpened> {
 }
 
 #[async_trait]
-impl Transition<ElevatorContext> for ElevatorState<DoorClosing> {
-    async fn on_event(
-        self: Box<Self>,
-        action: Action,
-        _ctx: &mut ElevatorContext,
-    ) -> anyhow::Result<BoxedTransition> {
+impl<C: CarContext> Transition<C> for ElevatorState<DoorClosing> {
+    async fn on_event(self: Box<Self>, action: Action, ctx: &mut C) -> TransitionResult<C> {
         match action {
             Action::DoorClosed => {
                 println!("Door Closed.");
@@ -312,6 +474,9 @@ impl Transition<ElevatorContext> for ElevatorState<DoorClosing> {
                 println!("Double Closing Door.");
                 Ok(self)
             }
+            Action::EmergencyStop => Ok(self.halt()),
+            Action::ClearingLamps => self.clear_lamps(ctx).await,
+            Action::Reset => self.reset(ctx).await,
             ev => {
                 eprintln!(
                     "Ignored: invalid schedule event {ev:?} in state {:?}",
@@ -325,3 +490,58 @@ impl Transition<ElevatorContext> for ElevatorState<DoorClosing> {
         State::DoorClosing
     }
 }
+
+#[async_trait]
+impl<C: CarContext> Transition<C> for ElevatorState<EmergencyBrake> {
+    async fn on_event(self: Box<Self>, action: Action, ctx: &mut C) -> TransitionResult<C> {
+        match action {
+            Action::Stopped => {
+                println!("Stopped after emergency stop.");
+                Ok(self)
+            }
+            Action::ClearingLamps => self.clear_lamps(ctx).await,
+            Action::Reset => self.reset(ctx).await,
+            ev => {
+                eprintln!(
+                    "Ignored: invalid schedule event {ev:?} in state {:?}",
+                    self._marker
+                );
+                Ok(self)
+            }
+        }
+    }
+    fn state(&self) -> State {
+        State::EmergencyBrake
+    }
+}
+
+#[async_trait]
+impl<C: CarContext> Transition<C> for ElevatorState<Homing> {
+    async fn on_event(self: Box<Self>, action: Action, ctx: &mut C) -> TransitionResult<C> {
+        match action {
+            Action::Braking => {
+                println!("Braking at the first floor found.");
+                let this = self.command(Command::S).await?;
+                ctx.level();
+                Ok(this.transit::<Braking>().boxed())
+            }
+            Action::EmergencyStop => {
+                let this = self.command(Command::S).await?;
+                Ok(this.halt())
+            }
+            Action::ClearingLamps => self.clear_lamps(ctx).await,
+            Action::Reset => self.reset(ctx).await,
+            ev => {
+                eprintln!(
+                    "Ignored: invalid schedule event {ev:?} in state {:?}",
+                    self._marker
+                );
+                Ok(self)
+            }
+        }
+    }
+
+    fn state(&self) -> State {
+        State::Homing
+    }
+}
@@ -0,0 +1,110 @@
+use crate::transition::State;
+use crate::types::sched_events::Action;
+use std::fmt::Write;
+
+/// Every `(state, action) -> state` edge the state machine accepts. Any pair
+/// missing from this table is ignored and leaves the machine where it was.
+pub const TRANSITIONS: &[(State, Action, State)] = &[
+    (State::Idle, Action::MovingUp, State::MovingUp),
+    (State::Idle, Action::MovingDown, State::MovingDown),
+    (State::Idle, Action::OpeningDoor, State::DoorOpening),
+    (State::Idle, Action::EmergencyStop, State::EmergencyBrake),
+    (State::Idle, Action::Reset, State::Idle),
+    (State::Idle, Action::ClearingLamps, State::Idle),
+    (State::MovingUp, Action::Braking, State::Braking),
+    (
+        State::MovingUp,
+        Action::EmergencyStop,
+        State::EmergencyBrake,
+    ),
+    (State::MovingUp, Action::Reset, State::Idle),
+    (State::MovingUp, Action::ClearingLamps, State::MovingUp),
+    (State::MovingDown, Action::Braking, State::Braking),
+    (
+        State::MovingDown,
+        Action::EmergencyStop,
+        State::EmergencyBrake,
+    ),
+    (State::MovingDown, Action::Reset, State::Idle),
+    (State::MovingDown, Action::ClearingLamps, State::MovingDown),
+    (State::Braking, Action::Stopped, State::Idle),
+    (State::Braking, Action::EmergencyStop, State::EmergencyBrake),
+    (State::Braking, Action::Reset, State::Idle),
+    (State::Braking, Action::ClearingLamps, State::Braking),
+    (State::DoorOpening, Action::DoorOpened, State::DoorOpened),
+    (State::DoorOpening, Action::OpeningDoor, State::DoorOpening),
+    (
+        State::DoorOpening,
+        Action::EmergencyStop,
+        State::EmergencyBrake,
+    ),
+    (State::DoorOpening, Action::Reset, State::Idle),
+    (
+        State::DoorOpening,
+        Action::ClearingLamps,
+        State::DoorOpening,
+    ),
+    (State::DoorOpened, Action::ClosingDoor, State::DoorClosing),
+    (State::DoorOpened, Action::HoldingDoor, State::DoorOpened),
+    (
+        State::DoorOpened,
+        Action::EmergencyStop,
+        State::EmergencyBrake,
+    ),
+    (State::DoorOpened, Action::Reset, State::Idle),
+    (State::DoorOpened, Action::ClearingLamps, State::DoorOpened),
+    (State::DoorClosing, Action::DoorClosed, State::Idle),
+    (State::DoorClosing, Action::ClosingDoor, State::DoorClosing),
+    (
+        State::DoorClosing,
+        Action::EmergencyStop,
+        State::EmergencyBrake,
+    ),
+    (State::DoorClosing, Action::Reset, State::Idle),
+    (
+        State::DoorClosing,
+        Action::ClearingLamps,
+        State::DoorClosing,
+    ),
+    (
+        State::EmergencyBrake,
+        Action::Stopped,
+        State::EmergencyBrake,
+    ),
+    (State::EmergencyBrake, Action::Reset, State::Idle),
+    (
+        State::EmergencyBrake,
+        Action::ClearingLamps,
+        State::EmergencyBrake,
+    ),
+    (State::Homing, Action::Braking, State::Braking),
+    (State::Homing, Action::EmergencyStop, State::EmergencyBrake),
+    (State::Homing, Action::Reset, State::Idle),
+    (State::Homing, Action::ClearingLamps, State::Homing),
+];
+
+/// The state `action` leads to from `state`, or `None` if the action is ignored there.
+pub fn next_state(state: State, action: Action) -> Option<State> {
+    TRANSITIONS
+        .iter()
+        .find(|(from, on, _)| *from == state && *on == action)
+        .map(|(_, _, to)| *to)
+}
+
+pub fn to_dot() -> String {
+    let mut out =
+        String::from("digraph elevator {\n    rankdir=LR;\n    Idle [shape=doublecircle];\n");
+    for (from, action, to) in TRANSITIONS {
+        let _ = writeln!(out, "    {from:?} -> {to:?} [label=\"{action:?}\"];");
+    }
+    out.push_str("}\n");
+    out
+}
+
+pub fn to_mermaid() -> String {
+    let mut out = String::from("stateDiagram-v2\n    [*] --> Idle\n");
+    for (from, action, to) in TRANSITIONS {
+        let _ = writeln!(out, "    {from:?} --> {to:?}: {action:?}");
+    }
+    out
+}
@@ -1,6 +1,8 @@
-use std::fmt;
-use std::fmt::Display;
+/// Commands that must reach the hardware together, in order, e.g. clearing
+/// a floor's lamps and opening the door.
+pub type CommandBatch = Vec<Command>;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Command {
     MU,
     MD,
@@ -16,21 +18,21 @@ pub enum Command {
     R,
 }
 
-impl Display for Command {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Command {
+    pub fn name(&self) -> &'static str {
         match self {
-            Command::MU => write!(f, "MU"),
-            Command::MD => write!(f, "MD"),
-            Command::S => write!(f, "S"),
-            Command::DO => write!(f, "DO"),
-            Command::DC => write!(f, "DC"),
-            Command::R => write!(f, "R"),
-            Command::CP(v) => write!(f, "CP{v}"),
-            Command::CU(v) => write!(f, "CU{v}"),
-            Command::CD(v) => write!(f, "CD{v}"),
-            Command::IU(v) => write!(f, "IU{v}"),
-            Command::ID(v) => write!(f, "ID{v}"),
-            Command::CI(v) => write!(f, "CI{v}"),
+            Command::MU => "MU",
+            Command::MD => "MD",
+            Command::S => "S",
+            Command::DO => "DO",
+            Command::DC => "DC",
+            Command::R => "R",
+            Command::CP(_) => "CP",
+            Command::CU(_) => "CU",
+            Command::CD(_) => "CD",
+            Command::IU(_) => "IU",
+            Command::ID(_) => "ID",
+            Command::CI(_) => "CI",
         }
     }
 }
@@ -0,0 +1,181 @@
+//! The Lifty wire protocol in both directions, shared by the controller and
+//! the `lify` simulator so the two cannot drift apart.
+//!
+//! Every message is a short ASCII line: a letter code followed by an optional
+//! decimal argument, e.g. `A3` or `CP2` or `MU`. A datagram may carry several
+//! messages separated by newlines. Floors are decoded as sent; which of
+//! them the building has is for the receiver to check.
+
+use crate::error::{Error, Result};
+use crate::types::cmd::Command;
+use crate::types::event::Event;
+use std::fmt;
+
+/// Splits a message into its letter code and argument. Arguments are plain
+/// canonical decimals that fit in a `u8`, so decoding then encoding gives
+/// back the same bytes.
+fn split(message: &[u8]) -> Result<(&str, Option<u8>)> {
+    let message = str::from_utf8(message.trim_ascii())
+        .map_err(|_| Error::InvalidPacket("message is not valid UTF‑8".to_string()))?;
+    let digits_at = message
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(message.len());
+    let (code, digits) = message.split_at(digits_at);
+    if code.is_empty() || !code.bytes().all(|b| b.is_ascii_uppercase()) {
+        return Err(Error::InvalidPacket(format!(
+            "bad message code in '{message}'"
+        )));
+    }
+    if digits.is_empty() {
+        return Ok((code, None));
+    }
+    if !digits.bytes().all(|b| b.is_ascii_digit()) || (digits.starts_with('0') && digits != "0") {
+        return Err(Error::InvalidPacket(format!(
+            "argument '{digits}' is not a number"
+        )));
+    }
+    let arg = digits
+        .parse::<u8>()
+        .map_err(|_| Error::InvalidPacket(format!("argument '{digits}' out of range")))?;
+    Ok((code, Some(arg)))
+}
+
+impl Event {
+    /// The wire code and argument, or `None` for operator events, which are
+    /// injected locally and never travel over UDP.
+    fn wire(&self) -> Option<(&'static str, u8)> {
+        match *self {
+            Event::ElevatorUp(n) => Some(("U", n)),
+            Event::ElevatorDown(n) => Some(("D", n)),
+            Event::PanelButtonPressed(n) => Some(("P", n)),
+            Event::ElevatorApproaching(n) => Some(("A", n)),
+            Event::ElevatorStopped(n) => Some(("S", n)),
+            Event::DoorOpened(n) => Some(("O", n)),
+            Event::DoorClosed(n) => Some(("C", n)),
+            Event::KeySwitched(n) => Some(("K", n)),
+            Event::LoadWeighed(n) => Some(("W", n)),
+            Event::CallCancelled(_)
+            | Event::EmergencyStop
+            | Event::Reset
+            | Event::StrategySwitched(_)
+            | Event::Restored => None,
+        }
+    }
+
+    /// Encodes a hardware event as Lifty sends it; operator events have no
+    /// wire form.
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        self.wire()
+            .map(|(code, n)| format!("{code}{n}").into_bytes())
+    }
+
+    /// Parses every newline-separated event in a datagram, in order. Lifty
+    /// may pack several events into one packet and terminals add `\r\n`;
+    /// blank lines are skipped and a bad line does not hide the good ones.
+    pub fn parse_datagram(datagram: &[u8]) -> Vec<Result<Event>> {
+        lines(datagram).map(Event::try_from).collect()
+    }
+}
+
+impl TryFrom<&[u8]> for Event {
+    type Error = Error;
+
+    fn try_from(value: &[u8]) -> Result<Self> {
+        let (code, arg) = split(value)?;
+        let Some(arg) = arg else {
+            return Err(Error::InvalidPacket(format!(
+                "event '{code}' has no argument"
+            )));
+        };
+        match code {
+            "U" => Ok(Event::ElevatorUp(arg)),
+            "D" => Ok(Event::ElevatorDown(arg)),
+            "P" => Ok(Event::PanelButtonPressed(arg)),
+            "A" => Ok(Event::ElevatorApproaching(arg)),
+            "S" => Ok(Event::ElevatorStopped(arg)),
+            "O" => Ok(Event::DoorOpened(arg)),
+            "C" => Ok(Event::DoorClosed(arg)),
+            "K" => Ok(Event::KeySwitched(arg)),
+            "W" => Ok(Event::LoadWeighed(arg)),
+            other => Err(Error::InvalidPacket(format!("unknown event code: {other}"))),
+        }
+    }
+}
+
+impl Command {
+    fn arg(&self) -> Option<u8> {
+        match *self {
+            Command::MU | Command::MD | Command::S | Command::DO | Command::DC | Command::R => None,
+            Command::CP(n)
+            | Command::CU(n)
+            | Command::CD(n)
+            | Command::IU(n)
+            | Command::ID(n)
+            | Command::CI(n) => Some(n),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+
+    /// Encodes commands as one newline-separated datagram.
+    pub fn encode_batch(commands: &[Command]) -> Vec<u8> {
+        commands
+            .iter()
+            .map(Command::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+            .into_bytes()
+    }
+
+    /// Parses every newline-separated command in a datagram, in order.
+    pub fn parse_datagram(datagram: &[u8]) -> Vec<Result<Command>> {
+        lines(datagram).map(Command::try_from).collect()
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())?;
+        match self.arg() {
+            Some(n) => write!(f, "{n}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for Command {
+    type Error = Error;
+
+    fn try_from(value: &[u8]) -> Result<Self> {
+        let command = match split(value)? {
+            ("MU", None) => Command::MU,
+            ("MD", None) => Command::MD,
+            ("S", None) => Command::S,
+            ("DO", None) => Command::DO,
+            ("DC", None) => Command::DC,
+            ("R", None) => Command::R,
+            ("CP", Some(n)) => Command::CP(n),
+            ("CU", Some(n)) => Command::CU(n),
+            ("CD", Some(n)) => Command::CD(n),
+            ("IU", Some(n)) => Command::IU(n),
+            ("ID", Some(n)) => Command::ID(n),
+            ("CI", Some(n)) => Command::CI(n),
+            _ => {
+                return Err(Error::InvalidPacket(format!(
+                    "unknown command: {}",
+                    String::from_utf8_lossy(value.trim_ascii())
+                )));
+            }
+        };
+        Ok(command)
+    }
+}
+
+fn lines(datagram: &[u8]) -> impl Iterator<Item = &[u8]> {
+    datagram
+        .split(|&b| b == b'\n')
+        .map(|line| line.trim_ascii())
+        .filter(|line| !line.is_empty())
+}
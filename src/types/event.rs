@@ -1,6 +1,3 @@
-use anyhow::Context;
-use std::convert::TryFrom;
-
 #[derive(Debug, Clone, PartialOrd, PartialEq)]
 pub enum Event {
     ElevatorUp(u8),
@@ -11,35 +8,48 @@ pub enum Event {
     DoorOpened(u8),
     DoorClosed(u8),
     KeySwitched(u8),
+    /// The car's load sensor, in percent of rated capacity (`Wn`).
+    LoadWeighed(u8),
+    // Operator events, injected locally rather than received from the hardware.
+    CallCancelled(u8),
+    EmergencyStop,
+    Reset,
+    StrategySwitched(String),
+    /// The controller took over a car from a checkpoint and may have calls to serve.
+    Restored,
 }
 
-impl TryFrom<&[u8]> for Event {
-    type Error = anyhow::Error;
-
-    fn try_from(value: &[u8]) -> anyhow::Result<Self> {
-        if value.len() < 2 {
-            anyhow::bail!(
-                "buffer too short: expected at least 2 bytes, got {}",
-                value.len()
-            );
+impl Event {
+    /// The floor a hardware event names. Key positions and loads are not floors.
+    pub fn floor(&self) -> Option<u8> {
+        match *self {
+            Event::ElevatorUp(n)
+            | Event::ElevatorDown(n)
+            | Event::PanelButtonPressed(n)
+            | Event::ElevatorApproaching(n)
+            | Event::ElevatorStopped(n)
+            | Event::DoorOpened(n)
+            | Event::DoorClosed(n) => Some(n),
+            _ => None,
         }
+    }
 
-        let cmd = value[0];
-        let num_str = str::from_utf8(&value[1..]).context("argument is not valid UTF‑8")?;
-        let arg = num_str
-            .parse::<u8>()
-            .context(format!("failed to parse '{num_str}' as u8"))?;
-
-        match cmd {
-            b'U' => Ok(Event::ElevatorUp(arg)),
-            b'D' => Ok(Event::ElevatorDown(arg)),
-            b'P' => Ok(Event::PanelButtonPressed(arg)),
-            b'A' => Ok(Event::ElevatorApproaching(arg)),
-            b'S' => Ok(Event::ElevatorStopped(arg)),
-            b'O' => Ok(Event::DoorOpened(arg)),
-            b'C' => Ok(Event::DoorClosed(arg)),
-            b'K' => Ok(Event::KeySwitched(arg)),
-            other => anyhow::bail!("unknown event byte: {}", other),
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::ElevatorUp(_) => "ElevatorUp",
+            Event::ElevatorDown(_) => "ElevatorDown",
+            Event::PanelButtonPressed(_) => "PanelButtonPressed",
+            Event::ElevatorApproaching(_) => "ElevatorApproaching",
+            Event::ElevatorStopped(_) => "ElevatorStopped",
+            Event::DoorOpened(_) => "DoorOpened",
+            Event::DoorClosed(_) => "DoorClosed",
+            Event::KeySwitched(_) => "KeySwitched",
+            Event::LoadWeighed(_) => "LoadWeighed",
+            Event::CallCancelled(_) => "CallCancelled",
+            Event::EmergencyStop => "EmergencyStop",
+            Event::Reset => "Reset",
+            Event::StrategySwitched(_) => "StrategySwitched",
+            Event::Restored => "Restored",
         }
     }
 }
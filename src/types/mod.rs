@@ -1,3 +1,5 @@
 pub mod cmd;
+pub mod codec;
 pub mod event;
+pub mod plan;
 pub mod sched_events;
@@ -0,0 +1,185 @@
+use crate::context::{CarContext, Location};
+use crate::transition::State;
+use std::collections::VecDeque;
+use std::fmt::{self, Display};
+
+/// What must still hold when a planned step comes up, or the step is stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precondition {
+    /// The machine is in this state.
+    InState(State),
+    /// The car stands at this floor.
+    AtFloor(u8),
+    /// The car is not over capacity.
+    WithinCapacity,
+}
+
+impl Precondition {
+    pub fn holds(&self, state: State, ctx: &impl CarContext) -> bool {
+        match *self {
+            Precondition::InState(expected) => state == expected,
+            Precondition::AtFloor(floor) => ctx.location() == Location::AtFloor(floor),
+            Precondition::WithinCapacity => !ctx.overloaded(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlanStep<E> {
+    pub event: E,
+    pub requires: Vec<Precondition>,
+    /// The target this step sets off for. Planning already took it from its
+    /// queue, so it goes back there if the step does not run.
+    pub target: Option<u8>,
+}
+
+impl<E> PlanStep<E> {
+    /// The first precondition that no longer holds, if any.
+    pub fn violated(&self, state: State, ctx: &impl CarContext) -> Option<Precondition> {
+        self.requires
+            .iter()
+            .find(|precondition| !precondition.holds(state, ctx))
+            .copied()
+    }
+}
+
+/// Steps a strategy wants carried out in order. Each step is checked against
+/// the car right before it runs, so a step a newer event made stale is skipped.
+#[derive(Debug, Clone)]
+pub struct Plan<E> {
+    steps: VecDeque<PlanStep<E>>,
+}
+
+impl<E> Default for Plan<E> {
+    fn default() -> Self {
+        Plan {
+            steps: VecDeque::new(),
+        }
+    }
+}
+
+impl<E> Plan<E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a step that always runs.
+    pub fn then(&mut self, event: E) -> &mut Self {
+        self.then_if(event, [])
+    }
+
+    /// Appends a step that only runs while every precondition still holds.
+    pub fn then_if(
+        &mut self,
+        event: E,
+        requires: impl IntoIterator<Item = Precondition>,
+    ) -> &mut Self {
+        self.steps.push_back(PlanStep {
+            event,
+            requires: requires.into_iter().collect(),
+            target: None,
+        });
+        self
+    }
+
+    /// Appends a step that sets off for `target` while every precondition
+    /// still holds.
+    pub fn then_toward(
+        &mut self,
+        target: u8,
+        event: E,
+        requires: impl IntoIterator<Item = Precondition>,
+    ) -> &mut Self {
+        self.then_if(event, requires);
+        if let Some(step) = self.steps.back_mut() {
+            step.target = Some(target);
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn next_step(&mut self) -> Option<PlanStep<E>> {
+        self.steps.pop_front()
+    }
+
+    /// Puts a step back at the head, e.g. one that has to wait.
+    pub fn push_front(&mut self, step: PlanStep<E>) {
+        self.steps.push_front(step);
+    }
+
+    pub fn steps(&self) -> impl Iterator<Item = &PlanStep<E>> {
+        self.steps.iter()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Executed,
+    /// Dropped because the precondition no longer held.
+    Skipped(Precondition),
+    Failed,
+    /// Not attempted because an earlier step failed.
+    Abandoned,
+}
+
+impl StepOutcome {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StepOutcome::Executed => "executed",
+            StepOutcome::Skipped(_) => "skipped",
+            StepOutcome::Failed => "failed",
+            StepOutcome::Abandoned => "abandoned",
+        }
+    }
+}
+
+/// How each step of a plan went, in plan order.
+#[derive(Debug, Clone)]
+pub struct PlanReport<E> {
+    pub steps: Vec<(E, StepOutcome)>,
+}
+
+impl<E> Default for PlanReport<E> {
+    fn default() -> Self {
+        PlanReport { steps: Vec::new() }
+    }
+}
+
+impl<E> PlanReport<E> {
+    pub fn record(&mut self, event: E, outcome: StepOutcome) {
+        self.steps.push((event, outcome));
+    }
+
+    pub fn executed(&self) -> impl Iterator<Item = &E> {
+        self.with(|outcome| outcome == StepOutcome::Executed)
+    }
+
+    pub fn skipped(&self) -> impl Iterator<Item = &E> {
+        self.with(|outcome| matches!(outcome, StepOutcome::Skipped(_)))
+    }
+
+    fn with(&self, keep: impl Fn(StepOutcome) -> bool) -> impl Iterator<Item = &E> {
+        self.steps
+            .iter()
+            .filter(move |(_, outcome)| keep(*outcome))
+            .map(|(event, _)| event)
+    }
+}
+
+impl<E: fmt::Debug> Display for PlanReport<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "plan:")?;
+        for (event, outcome) in &self.steps {
+            match outcome {
+                StepOutcome::Skipped(precondition) => {
+                    write!(f, " {event:?}=skipped({precondition:?})")?
+                }
+                outcome => write!(f, " {event:?}={}", outcome.kind())?,
+            }
+        }
+        Ok(())
+    }
+}
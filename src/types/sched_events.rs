@@ -1,12 +1,15 @@
+use crate::error::Anomaly;
 use std::time::Duration;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ScheduleEvent {
     Instant(Action),
     WaitTime(Duration, Action),
+    /// Reported by a strategy when the hardware does something it did not expect.
+    Anomaly(Anomaly),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Action {
     MovingUp,
     MovingDown,
@@ -16,4 +19,27 @@ pub enum Action {
     ClosingDoor,
     DoorOpened,
     DoorClosed,
+    EmergencyStop,
+    Reset,
+    /// Clear the lamps of calls dropped without a stop, e.g. cancelled ones.
+    ClearingLamps,
+    /// Keep the door open past its time, while the car is over capacity.
+    HoldingDoor,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::MovingUp,
+        Action::MovingDown,
+        Action::Braking,
+        Action::Stopped,
+        Action::OpeningDoor,
+        Action::ClosingDoor,
+        Action::DoorOpened,
+        Action::DoorClosed,
+        Action::EmergencyStop,
+        Action::Reset,
+        Action::ClearingLamps,
+        Action::HoldingDoor,
+    ];
 }
@@ -0,0 +1,191 @@
+mod common;
+
+use common::{Sent, queued};
+use elevator::car;
+use elevator::checkpoint::{Checkpoint, MAX_CHECKPOINT_AGE};
+use elevator::config::Startup;
+use elevator::context::{Call, ElevatorContext};
+use elevator::position::{Direction, Position};
+use elevator::transition::{ElevatorState, PreStart, State};
+use elevator::types::cmd::Command;
+use std::cmp::Reverse;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn checkpoint(state: State, position: Position) -> Checkpoint {
+    let mut context = ElevatorContext::new(1, 5);
+    context.position = position;
+    context.direction_up = false;
+    context.active_target = Some(2);
+    context.key = 2;
+    context.up_queue.push(Reverse(4));
+    context.down_queue.extend([2, 1]);
+    context
+        .calls
+        .extend([(1, Call::Car), (2, Call::HallDown), (4, Call::HallUp)]);
+    Checkpoint {
+        saved_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        state,
+        strategy: Some("scan".to_string()),
+        context,
+    }
+}
+
+#[test]
+fn checkpoints_round_trip() {
+    let saved = checkpoint(
+        State::MovingDown,
+        Position::Departing {
+            from: 3,
+            direction: Direction::Down,
+        },
+    );
+    let text = saved.encode();
+    assert_eq!(
+        text,
+        "saved 1700000000\nstate MovingDown\nstrategy scan\nposition departing 3 down\ndirection down\ntarget 2\nkey 2\nup 4\ndown 1 2\ncalls P1 D2 U4\n"
+    );
+
+    let loaded = Checkpoint::decode(&text, 1, 5).unwrap();
+    assert_eq!(loaded.saved_at, saved.saved_at);
+    assert_eq!(loaded.state, saved.state);
+    assert_eq!(loaded.strategy, saved.strategy);
+    assert_eq!(loaded.encode(), text);
+    assert_eq!(loaded.context.calls, saved.context.calls);
+    assert_eq!(
+        Vec::from_iter(loaded.pending_calls()),
+        [(1, Call::Car), (2, Call::HallDown), (4, Call::HallUp)]
+    );
+}
+
+#[test]
+fn checkpoints_outside_the_building_are_rejected() {
+    let text = checkpoint(State::Idle, Position::Stopped(3)).encode();
+    assert!(Checkpoint::decode(&text, 1, 3).is_err());
+    assert!(Checkpoint::decode(&text.replace("state Idle", "state Flying"), 1, 5).is_err());
+    assert!(Checkpoint::decode(&text.replace("position 3\n", ""), 1, 5).is_err());
+    assert!(Checkpoint::decode(&text.replace("calls P1", "calls X1"), 1, 5).is_err());
+    let off_the_top = text.replace("position 3", "position departing 5 up");
+    assert!(Checkpoint::decode(&off_the_top, 1, 5).is_err());
+}
+
+#[test]
+fn only_fresh_checkpoints_at_rest_are_warm() {
+    let idle = checkpoint(State::Idle, Position::Stopped(3));
+    let now = idle.saved_at + Duration::from_secs(5);
+    assert!(idle.is_warm(now));
+    assert!(checkpoint(State::DoorOpened, Position::Stopped(3)).is_warm(now));
+    assert!(!idle.is_warm(idle.saved_at + MAX_CHECKPOINT_AGE + Duration::from_secs(1)));
+    assert!(
+        !checkpoint(
+            State::MovingUp,
+            Position::Approaching {
+                floor: 4,
+                direction: Direction::Up,
+            }
+        )
+        .is_warm(now)
+    );
+    assert!(!checkpoint(State::DoorClosing, Position::Stopped(3)).is_warm(now));
+}
+
+async fn restore(saved: Checkpoint) -> (car::CarHandle, Sent) {
+    restore_with(saved, Startup::Reset).await
+}
+
+async fn restore_with(mut saved: Checkpoint, startup: Startup) -> (car::CarHandle, Sent) {
+    saved.saved_at = SystemTime::now();
+    let (tx, sent) = common::loopback();
+    let car = car::restore(ElevatorState::<PreStart>::new(tx), saved, startup);
+    (car, sent)
+}
+
+#[tokio::test]
+async fn warm_start_takes_over_without_a_reset() {
+    let (car, sent) = restore(checkpoint(State::DoorOpened, Position::Stopped(3))).await;
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.state, State::DoorClosing);
+    assert_eq!(snapshot.context.position, Position::Stopped(3));
+    assert_eq!(snapshot.context.key, 2);
+    assert_eq!(*sent.lock().unwrap(), [Command::DC]);
+}
+
+#[tokio::test]
+async fn unsafe_checkpoint_resets_but_keeps_the_calls() {
+    let (car, sent) = restore(checkpoint(
+        State::MovingUp,
+        Position::Approaching {
+            floor: 4,
+            direction: Direction::Up,
+        },
+    ))
+    .await;
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.state, State::Idle);
+    assert_eq!(snapshot.context.position, Position::Stopped(1));
+    // The hall call down at 2 is still served on the way down.
+    assert_eq!(queued(&snapshot.context), (vec![1, 4], vec![2]));
+    assert_eq!(
+        Vec::from_iter(snapshot.context.calls),
+        [(1, Call::Car), (2, Call::HallDown), (4, Call::HallUp)]
+    );
+    assert_eq!(*sent.lock().unwrap(), [Command::R]);
+}
+
+#[tokio::test]
+async fn homing_after_a_restore_holds_the_calls() {
+    let saved = checkpoint(
+        State::MovingUp,
+        Position::Approaching {
+            floor: 4,
+            direction: Direction::Up,
+        },
+    );
+    let (car, _) = restore_with(saved, Startup::Home { up: false }).await;
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.state, State::Homing);
+    assert_eq!(queued(&snapshot.context), (vec![], vec![]));
+    assert_eq!(
+        Vec::from_iter(snapshot.context.calls),
+        [(1, Call::Car), (2, Call::HallDown), (4, Call::HallUp)]
+    );
+}
+
+#[tokio::test]
+async fn cold_start_keeps_a_target_that_was_never_queued() {
+    let target_only = |state, position| {
+        let mut saved = checkpoint(state, position);
+        saved.context.up_queue.clear();
+        saved.context.down_queue.clear();
+        saved.context.calls.clear();
+        saved.context.active_target = Some(3);
+        saved
+    };
+    let approaching = target_only(
+        State::MovingUp,
+        Position::Approaching {
+            floor: 3,
+            direction: Direction::Up,
+        },
+    );
+    // Reset before its door opened, the car still owes the floor it stood at.
+    let opening = target_only(State::DoorOpening, Position::Stopped(3));
+    for saved in [approaching, opening] {
+        assert_eq!(Vec::from_iter(saved.pending_calls()), [(3, Call::Car)]);
+        let (car, _) = restore(saved).await;
+        let snapshot = car.snapshot().await.unwrap();
+        assert_eq!(snapshot.context.position, Position::Stopped(1));
+        assert_eq!(queued(&snapshot.context), (vec![3], vec![]));
+    }
+}
+
+#[tokio::test]
+async fn checkpoints_survive_a_save_and_load() {
+    let path = std::env::temp_dir().join(format!("elevator-checkpoint-{}", std::process::id()));
+    assert!(Checkpoint::load(&path, 1, 5).await.unwrap().is_none());
+
+    let saved = checkpoint(State::Idle, Position::Stopped(3));
+    saved.save(&path).await.unwrap();
+    let loaded = Checkpoint::load(&path, 1, 5).await.unwrap().unwrap();
+    assert_eq!(loaded.encode(), saved.encode());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,121 @@
+use elevator::types::cmd::Command;
+use elevator::types::event::Event;
+use proptest::prelude::*;
+
+fn hardware_events(floor: u8, n: u8) -> [Event; 9] {
+    [
+        Event::ElevatorUp(floor),
+        Event::ElevatorDown(floor),
+        Event::PanelButtonPressed(floor),
+        Event::ElevatorApproaching(floor),
+        Event::ElevatorStopped(floor),
+        Event::DoorOpened(floor),
+        Event::DoorClosed(floor),
+        Event::KeySwitched(n),
+        Event::LoadWeighed(n),
+    ]
+}
+
+fn commands(n: u8) -> [Command; 12] {
+    [
+        Command::MU,
+        Command::MD,
+        Command::S,
+        Command::DO,
+        Command::DC,
+        Command::R,
+        Command::CP(n),
+        Command::CU(n),
+        Command::CD(n),
+        Command::IU(n),
+        Command::ID(n),
+        Command::CI(n),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn every_event_round_trips(floor in any::<u8>(), n in any::<u8>()) {
+        for event in hardware_events(floor, n) {
+            let bytes = event.to_bytes().unwrap();
+            prop_assert_eq!(Event::try_from(bytes.as_slice()).unwrap(), event);
+        }
+    }
+
+    #[test]
+    fn every_command_round_trips(n in any::<u8>()) {
+        for command in commands(n) {
+            let bytes = command.to_bytes();
+            prop_assert_eq!(Command::try_from(bytes.as_slice()).unwrap(), command);
+        }
+    }
+
+    #[test]
+    fn decoded_messages_encode_to_the_same_bytes(data in "[A-Z]{1,2}[0-9]{0,3}") {
+        if let Ok(event) = Event::try_from(data.as_bytes()) {
+            prop_assert_eq!(event.to_bytes().unwrap(), data.as_bytes());
+        }
+        if let Ok(command) = Command::try_from(data.as_bytes()) {
+            prop_assert_eq!(command.to_bytes(), data.as_bytes());
+        }
+    }
+}
+
+#[test]
+fn operator_events_have_no_wire_form() {
+    for event in [
+        Event::CallCancelled(1),
+        Event::EmergencyStop,
+        Event::Reset,
+        Event::StrategySwitched("scan".to_string()),
+        Event::Restored,
+    ] {
+        assert_eq!(event.to_bytes(), None);
+    }
+}
+
+#[test]
+fn command_datagrams_parse_in_order() {
+    let parsed: Vec<_> = Command::parse_datagram(b"CP3\r\nCU3\nS\n\nDO\nMX")
+        .into_iter()
+        .map(|c| c.ok())
+        .collect();
+    assert_eq!(
+        parsed,
+        [
+            Some(Command::CP(3)),
+            Some(Command::CU(3)),
+            Some(Command::S),
+            Some(Command::DO),
+            None
+        ]
+    );
+}
+
+#[test]
+fn commands_and_events_do_not_overlap() {
+    for n in 0..=9 {
+        for event in hardware_events(n, n) {
+            let bytes = event.to_bytes().unwrap();
+            assert!(Command::try_from(bytes.as_slice()).is_err(), "{event:?}");
+        }
+        for command in commands(n) {
+            assert!(
+                Event::try_from(command.to_bytes().as_slice()).is_err(),
+                "{command:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn command_batches_round_trip_as_one_datagram() {
+    let batch = [Command::CP(2), Command::CU(2), Command::CD(2), Command::DO];
+    let datagram = Command::encode_batch(&batch);
+    assert_eq!(datagram, b"CP2\nCU2\nCD2\nDO");
+    let parsed: Vec<_> = Command::parse_datagram(&datagram)
+        .into_iter()
+        .map(|c| c.unwrap())
+        .collect();
+    assert_eq!(parsed, batch);
+}
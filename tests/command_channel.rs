@@ -0,0 +1,135 @@
+use elevator::car;
+use elevator::command_channel::{self, COMMAND_CHANNEL_CAPACITY};
+use elevator::config::{ControllerConfig, FaultPolicy, FlushPolicy};
+use elevator::context::ElevatorContext;
+use elevator::error::{Error, ErrorClass};
+use elevator::metrics::Metrics;
+use elevator::services::controller::ControllerService;
+use elevator::services::scheduler::SchedulerEventLayer;
+use elevator::strategies::scan::ScanStrategy;
+use elevator::transition::State;
+use elevator::types::cmd::Command;
+use elevator::types::event::Event;
+use elevator::types::sched_events::Action;
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tower::{Layer, Service, ServiceExt};
+
+/// An IPv4 socket cannot send to an IPv6 peer, so every write fails.
+const UNREACHABLE: &str = "[::1]:9";
+
+/// Queues two stops' batches, starts the sender and returns the datagrams
+/// the peer received.
+async fn send_with(flush: FlushPolicy, expected: usize) -> Vec<String> {
+    let peer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address: &'static str = peer.local_addr().unwrap().to_string().leak();
+    let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+
+    let (tx, rx) = command_channel::channel(COMMAND_CHANNEL_CAPACITY);
+    let first = tx
+        .enqueue(vec![Command::CP(2), Command::CU(2), Command::DO])
+        .await
+        .unwrap();
+    let second = tx.enqueue(vec![Command::DC]).await.unwrap();
+
+    let car = car::spawn(State::Idle.enter(tx), ElevatorContext::new(1, 5));
+    let controller = ControllerService::new(car, Arc::new(Metrics::default()), false);
+    controller
+        .run_background(socket, rx, address, flush)
+        .await
+        .unwrap();
+
+    first.sent().await.unwrap();
+    second.sent().await.unwrap();
+
+    let mut buf = [0u8; 2000];
+    let mut received = Vec::new();
+    for _ in 0..expected {
+        let len = peer.recv(&mut buf).await.unwrap();
+        received.push(String::from_utf8(buf[..len].to_vec()).unwrap());
+    }
+    received
+}
+
+#[tokio::test]
+async fn each_command_sends_one_datagram_per_command() {
+    let received = send_with(FlushPolicy::EachCommand, 4).await;
+    assert_eq!(received, ["CP2", "CU2", "DO", "DC"]);
+}
+
+#[tokio::test]
+async fn each_batch_keeps_a_stop_in_one_datagram() {
+    let received = send_with(FlushPolicy::EachBatch, 2).await;
+    assert_eq!(received, ["CP2\nCU2\nDO", "DC"]);
+}
+
+#[tokio::test]
+async fn coalesce_merges_queued_batches_within_the_limit() {
+    let received = send_with(FlushPolicy::Coalesce { max_bytes: 2000 }, 1).await;
+    assert_eq!(received, ["CP2\nCU2\nDO\nDC"]);
+
+    let received = send_with(FlushPolicy::Coalesce { max_bytes: 12 }, 2).await;
+    assert_eq!(received, ["CP2\nCU2\nDO", "DC"]);
+}
+
+#[tokio::test]
+async fn socket_errors_fail_the_transition_and_keep_the_machine() {
+    let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+    let (tx, rx) = command_channel::channel(COMMAND_CHANNEL_CAPACITY);
+    let car = car::spawn(State::Idle.enter(tx), ElevatorContext::new(1, 5));
+    let mut controller = ControllerService::new(car.clone(), Arc::new(Metrics::default()), false);
+    controller
+        .run_background(socket, rx, UNREACHABLE, FlushPolicy::EachBatch)
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        let result = controller
+            .ready()
+            .await
+            .unwrap()
+            .call(Action::MovingUp)
+            .await;
+        assert!(matches!(result, Err(Error::Transport(_))), "{result:?}");
+        assert_eq!(car.snapshot().await.unwrap().state, State::Idle);
+    }
+}
+
+#[tokio::test]
+async fn sends_fail_once_the_transport_is_gone() {
+    let (tx, rx) = command_channel::channel(1);
+    drop(rx);
+    let result = tx.send(vec![Command::R]).await;
+    assert!(matches!(result, Err(Error::CommandChannelClosed)));
+}
+
+#[tokio::test]
+async fn a_socket_that_keeps_failing_runs_the_fault_policy() {
+    let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+    let (tx, rx) = command_channel::channel(COMMAND_CHANNEL_CAPACITY);
+    let car = car::spawn(State::Idle.enter(tx), ElevatorContext::new(1, 5));
+    let metrics = Arc::new(Metrics::default());
+    let controller = ControllerService::new(car.clone(), metrics.clone(), false);
+    controller
+        .run_background(socket, rx, UNREACHABLE, FlushPolicy::EachBatch)
+        .await
+        .unwrap();
+    let config = ControllerConfig {
+        strict: false,
+        fault_policy: FaultPolicy::StopSafely,
+    };
+    let mut scheduler = SchedulerEventLayer::new(ScanStrategy::new(), car.clone(), config, metrics)
+        .layer(controller);
+
+    let error = scheduler
+        .ready()
+        .await
+        .unwrap()
+        .call(Event::PanelButtonPressed(3))
+        .await
+        .unwrap_err();
+    assert_eq!(error.class(), ErrorClass::Fault, "{error}");
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.state, State::EmergencyBrake);
+    assert_eq!(snapshot.context.pending().len(), 1, "the call is kept");
+}
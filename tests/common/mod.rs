@@ -0,0 +1,88 @@
+//! Fixtures shared by the integration tests. Each test binary uses a
+//! different subset of them.
+#![allow(dead_code)]
+
+use async_trait::async_trait;
+use elevator::car::CarHandle;
+use elevator::command_channel::{self, COMMAND_CHANNEL_CAPACITY, CommandSender};
+use elevator::config::ControllerConfig;
+use elevator::context::ElevatorContext;
+use elevator::decision::QueueSnapshot;
+use elevator::metrics::Metrics;
+use elevator::position::Position;
+use elevator::services::controller::ControllerService;
+use elevator::services::scheduler::{SchedulerEventLayer, SchedulerService};
+use elevator::strategies::scan::ScanStrategy;
+use elevator::strategy::{Handled, Strategy};
+use elevator::transition::State;
+use elevator::types::cmd::Command;
+use elevator::types::event::Event;
+use elevator::types::plan::Plan;
+use elevator::types::sched_events::{Action, ScheduleEvent};
+use std::sync::{Arc, Mutex};
+use tower::Layer;
+
+/// Every command the car sent, in order.
+pub type Sent = Arc<Mutex<Vec<Command>>>;
+
+/// A command channel whose batches are logged and confirmed as sent.
+pub fn loopback() -> (CommandSender, Sent) {
+    let sent = Sent::default();
+    let (tx, rx) = command_channel::channel(COMMAND_CHANNEL_CAPACITY);
+    let log = sent.clone();
+    command_channel::spawn_loopback(rx, move |batch| log.lock().unwrap().extend(batch));
+    (tx, sent)
+}
+
+/// The scheduler over a controller whose transport is already up.
+pub fn scheduler<ST>(
+    strategy: ST,
+    car: &CarHandle,
+    config: ControllerConfig,
+) -> (SchedulerService<ControllerService, ST>, Arc<Metrics>)
+where
+    ST: Clone,
+{
+    let metrics = Arc::new(Metrics::default());
+    let controller = ControllerService::new(car.clone(), metrics.clone(), config.strict);
+    controller.transport_up();
+    let scheduler =
+        SchedulerEventLayer::new(strategy, car.clone(), config, metrics.clone()).layer(controller);
+    (scheduler, metrics)
+}
+
+/// Hands out the same plan for every event.
+#[derive(Clone)]
+pub struct Fixed(pub Plan<ScheduleEvent>);
+
+#[async_trait]
+impl Strategy<Event, ScheduleEvent, CarHandle> for Fixed {
+    async fn handle(&self, _event: Event, _car: &CarHandle) -> Handled<ScheduleEvent> {
+        Some(self.0.clone()).into()
+    }
+}
+
+/// A car at rest at `floor` in a five-floor building, facing `up`.
+pub fn at(floor: u8, up: bool) -> ElevatorContext {
+    ElevatorContext {
+        position: Position::Stopped(floor),
+        direction_up: up,
+        ..ElevatorContext::new(1, 5)
+    }
+}
+
+/// The queued floors, up then down, lowest first.
+pub fn queued(ctx: &ElevatorContext) -> (Vec<u8>, Vec<u8>) {
+    let QueueSnapshot { up, down } = QueueSnapshot::of(ctx);
+    (up, down)
+}
+
+/// Whether the scan strategy brakes on `event` while the car moves up.
+pub fn brakes(event: Event, ctx: &mut ElevatorContext) -> bool {
+    ScanStrategy::plan(event, ctx, State::MovingUp)
+        .plan
+        .is_some_and(|plan| {
+            plan.steps()
+                .any(|step| matches!(step.event, ScheduleEvent::Instant(Action::Braking)))
+        })
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use common::{at, queued};
+use elevator::car;
+use elevator::console::{Console, status_line};
+use elevator::context::{Call, ElevatorContext};
+use elevator::decision::DecisionLog;
+use elevator::position::{Direction, Position};
+use elevator::strategies::scan::ScanStrategy;
+use elevator::transition::State;
+use elevator::types::cmd::Command;
+use elevator::types::event::Event;
+use elevator::types::sched_events::{Action, ScheduleEvent};
+use std::cmp::Reverse;
+
+fn console() -> Console {
+    let (tx, _) = common::loopback();
+    let car = car::spawn(State::Idle.enter(tx), ElevatorContext::new(1, 5));
+    Console::new(1..=5, 0..3, car, DecisionLog::default())
+}
+
+#[tokio::test]
+async fn lines_become_events() {
+    let mut console = console();
+    let cases = [
+        ("call 3", Event::PanelButtonPressed(3)),
+        ("UP 1", Event::ElevatorUp(1)),
+        ("down 5", Event::ElevatorDown(5)),
+        ("cancel 2", Event::CallCancelled(2)),
+        ("key 2", Event::KeySwitched(2)),
+        ("load 120", Event::LoadWeighed(120)),
+        ("stop", Event::EmergencyStop),
+        ("reset", Event::Reset),
+        ("strategy scan", Event::StrategySwitched("scan".to_string())),
+    ];
+    for (line, event) in cases {
+        assert_eq!(console.parse(line), Ok(Some(event)), "{line}");
+    }
+    for line in ["", "  ", "status", "help", "why 3"] {
+        assert_eq!(console.parse(line), Ok(None), "{line}");
+    }
+}
+
+#[tokio::test]
+async fn bad_lines_are_rejected() {
+    let mut console = console();
+    for line in [
+        "call", "call 0", "call 6", "call x", "key 3", "load 256", "strategy", "why x", "fly 3",
+    ] {
+        assert!(console.parse(line).is_err(), "{line}");
+    }
+    assert_eq!(console.parse("call 6").unwrap_err(), "floor 6 out of range");
+    assert_eq!(console.handle_line("call 9"), None);
+}
+
+#[test]
+fn status_line_shows_the_car() {
+    let mut ctx = at(3, true);
+    ctx.active_target = Some(4);
+    ctx.up_queue.push(Reverse(5));
+    ctx.down_queue.extend([1, 2]);
+    ctx.key = 2;
+    assert_eq!(
+        status_line(&ctx, State::Idle),
+        "[ FLOOR 3 | Idle           ^^ | DOOR CLOSED  | TARGET 4 | U:[5] | D:[2, 1] | K2 ]"
+    );
+
+    let mut ctx = at(1, false);
+    ctx.position = Position::Departing {
+        from: 2,
+        direction: Direction::Down,
+    };
+    assert_eq!(
+        status_line(&ctx, State::MovingDown),
+        "[ FLOOR 1-2 | MovingDown     -- | DOOR CLOSED  | TARGET - | U:[] | D:[] | K0 ]"
+    );
+    assert!(status_line(&at(2, true), State::DoorOpened).contains("DOOR OPEN "));
+    assert!(status_line(&at(2, true), State::Homing).contains("FLOOR ?"));
+}
+
+#[test]
+fn cancelling_the_target_clears_its_lamps() {
+    let mut ctx = at(1, true);
+    ScanStrategy::plan(Event::PanelButtonPressed(4), &mut ctx, State::Idle);
+    ctx.depart(true);
+    ctx.enqueue_call(3, Call::HallUp);
+
+    let handled = ScanStrategy::plan(Event::CallCancelled(4), &mut ctx, State::MovingUp);
+    assert_eq!(
+        ctx.active_target,
+        Some(3),
+        "heads for the next call instead"
+    );
+    assert_eq!(queued(&ctx), (vec![], vec![]));
+    let plan = handled.plan.unwrap();
+    assert!(
+        plan.steps()
+            .any(|step| matches!(step.event, ScheduleEvent::Instant(Action::ClearingLamps)))
+    );
+    assert_eq!(
+        ctx.lamps_to_clear,
+        [(4, Call::Car), (4, Call::HallUp), (4, Call::HallDown)]
+    );
+}
+
+#[test]
+fn moving_car_with_nothing_left_stops_at_the_next_floor() {
+    let mut ctx = at(1, true);
+    ScanStrategy::plan(Event::PanelButtonPressed(4), &mut ctx, State::Idle);
+    ctx.depart(true);
+    ctx.approach(2);
+    assert!(ctx.cancel_request(4));
+    assert_eq!(ctx.active_target, Some(3));
+
+    assert!(!ctx.cancel_request(5));
+}
+
+#[tokio::test]
+async fn cancelled_lamps_are_cleared_by_the_car() {
+    let (tx, sent) = common::loopback();
+    let mut ctx = at(3, true);
+    ctx.enqueue_call(5, Call::HallDown);
+    ctx.cancel_request(5);
+    let car = car::spawn(State::Idle.enter(tx), ctx);
+
+    let applied = car.apply(Action::ClearingLamps, true).await.unwrap();
+    assert_eq!(applied.result.unwrap(), State::Idle);
+    assert_eq!(*sent.lock().unwrap(), [Command::CP(5), Command::CD(5)]);
+}
@@ -0,0 +1,151 @@
+use elevator::context::{ElevatorContext, Location};
+use elevator::position::Position;
+use proptest::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
+const MIN_FLOOR: u8 = 1;
+const MAX_FLOOR: u8 = 5;
+const MAX_STEPS: usize = 1000;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Request(u8),
+    Step,
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![(0..=MAX_FLOOR + 2).prop_map(Op::Request), Just(Op::Step)]
+}
+
+/// Drives the context the way the hardware does: depart, approach
+/// intermediate floors, level and stop only at the active target.
+struct Car {
+    ctx: ElevatorContext,
+    served: BTreeSet<u8>,
+}
+
+impl Car {
+    fn step(&mut self) {
+        let ctx = &mut self.ctx;
+        let Some(target) = ctx.active_target else {
+            ctx.next_target();
+            return;
+        };
+        match ctx.position {
+            Position::Stopped(f) if f == target => {
+                self.served.insert(target);
+                ctx.next_target();
+            }
+            Position::Stopped(_) => ctx.depart(ctx.direction_up),
+            Position::Departing { .. } => {
+                let next = ctx.position.next_floor().unwrap();
+                ctx.approach(next);
+            }
+            Position::Approaching { floor, direction } => {
+                if floor == target {
+                    ctx.level();
+                } else {
+                    ctx.approach(direction.step(floor).unwrap());
+                }
+            }
+            Position::Levelling { floor, .. } => ctx.stopped_at(floor),
+        }
+    }
+
+    fn check_invariants(&self) {
+        let ctx = &self.ctx;
+        match ctx.location() {
+            Location::AtFloor(f) => assert!(ctx.in_range(f), "at floor {f}"),
+            Location::BetweenFloors(l, h) => {
+                assert!(
+                    ctx.in_range(l) && ctx.in_range(h) && h == l + 1,
+                    "between {l} and {h}"
+                )
+            }
+        }
+
+        let mut pending: Vec<u8> = ctx.up_queue.iter().map(|&Reverse(f)| f).collect();
+        pending.extend(ctx.down_queue.iter().copied());
+        if ctx.active_target.map(Location::AtFloor) != Some(ctx.location()) {
+            pending.extend(ctx.active_target);
+        }
+        let unique: BTreeSet<u8> = pending.iter().copied().collect();
+        assert_eq!(
+            unique.len(),
+            pending.len(),
+            "duplicate targets in {pending:?}"
+        );
+        assert!(
+            pending.iter().all(|&f| ctx.in_range(f)),
+            "out of range in {pending:?}"
+        );
+    }
+}
+
+proptest! {
+    #[test]
+    fn every_request_is_eventually_served(ops in prop::collection::vec(op(), 0..200)) {
+        let mut car = Car {
+            ctx: ElevatorContext::new(MIN_FLOOR, MAX_FLOOR),
+            served: BTreeSet::new(),
+        };
+        let mut requested = BTreeSet::new();
+
+        for op in ops {
+            match op {
+                Op::Request(floor) => {
+                    car.ctx.enqueue_request(floor);
+                    if car.ctx.in_range(floor) {
+                        requested.insert(floor);
+                        car.served.remove(&floor);
+                    }
+                }
+                Op::Step => car.step(),
+            }
+            car.check_invariants();
+        }
+
+        for _ in 0..MAX_STEPS {
+            if car.ctx.active_target.is_none()
+                && car.ctx.up_queue.is_empty()
+                && car.ctx.down_queue.is_empty()
+            {
+                break;
+            }
+            car.step();
+            car.check_invariants();
+        }
+
+        prop_assert!(car.ctx.up_queue.is_empty() && car.ctx.down_queue.is_empty());
+        prop_assert_eq!(&requested, &car.served);
+    }
+
+    #[test]
+    fn out_of_range_floors_never_panic(floor in any::<u8>(), up in any::<bool>()) {
+        let mut ctx = ElevatorContext::new(MIN_FLOOR, MAX_FLOOR);
+        ctx.direction_up = up;
+        ctx.depart(up);
+        ctx.approach(floor);
+        ctx.enqueue_request(floor);
+        ctx.level();
+        ctx.stopped_at(floor);
+        let car = Car { ctx, served: BTreeSet::new() };
+        car.check_invariants();
+    }
+}
+
+#[test]
+fn request_on_current_floor_is_served() {
+    let mut ctx = ElevatorContext::new(MIN_FLOOR, MAX_FLOOR);
+    ctx.enqueue_request(MIN_FLOOR);
+    assert_eq!(ctx.next_target(), Some(MIN_FLOOR));
+}
+
+#[test]
+fn leaving_the_bottom_floor_downwards_stays_put() {
+    let mut ctx = ElevatorContext::new(MIN_FLOOR, MAX_FLOOR);
+    ctx.direction_up = false;
+    ctx.depart(false);
+    assert_eq!(ctx.position, Position::Stopped(MIN_FLOOR));
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use elevator::car;
+use elevator::context::{CarContext, ElevatorContext, Location};
+use elevator::position::Position;
+use elevator::transition::State;
+use elevator::types::cmd::Command;
+use elevator::types::sched_events::Action;
+use std::ops::RangeInclusive;
+
+/// A building context carrying its own sensor data next to the stock queues.
+#[derive(Debug, Clone)]
+struct WeighedContext {
+    inner: ElevatorContext,
+    load_kg: u16,
+    resets: usize,
+}
+
+impl CarContext for WeighedContext {
+    fn position(&self) -> Position {
+        self.inner.position
+    }
+
+    fn floors(&self) -> RangeInclusive<u8> {
+        self.inner.floors()
+    }
+
+    fn depart(&mut self, up: bool) {
+        self.inner.depart(up)
+    }
+
+    fn approach(&mut self, floor: u8) {
+        self.inner.approach(floor)
+    }
+
+    fn level(&mut self) {
+        self.inner.level()
+    }
+
+    fn stopped_at(&mut self, floor: u8) {
+        self.inner.stopped_at(floor)
+    }
+
+    fn set_direction(&mut self, up: bool) {
+        self.inner.set_direction(up)
+    }
+
+    fn enqueue_request(&mut self, floor: u8) {
+        self.inner.enqueue_request(floor)
+    }
+
+    fn cancel_request(&mut self, floor: u8) -> bool {
+        self.inner.cancel_request(floor)
+    }
+
+    fn clear_requests(&mut self) {
+        self.inner.clear_requests()
+    }
+
+    fn next_target(&mut self) -> Option<u8> {
+        self.inner.next_target()
+    }
+
+    fn reset(&mut self) {
+        self.resets += 1;
+        self.inner.reset()
+    }
+}
+
+#[tokio::test]
+async fn car_runs_on_a_custom_context() {
+    let (tx, sent) = common::loopback();
+    let context = WeighedContext {
+        inner: ElevatorContext::new(1, 5),
+        load_kg: 320,
+        resets: 0,
+    };
+    let car = car::spawn(State::Idle.enter(tx), context);
+
+    let applied = car.apply(Action::MovingUp, true).await.unwrap();
+    assert_eq!(applied.result.unwrap(), State::MovingUp);
+    let applied = car.apply(Action::Reset, true).await.unwrap();
+    assert_eq!(applied.result.unwrap(), State::Idle);
+
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.context.location(), Location::AtFloor(1));
+    assert_eq!(snapshot.context.load_kg, 320);
+    assert_eq!(snapshot.context.resets, 1);
+    assert_eq!(*sent.lock().unwrap(), vec![Command::MU, Command::R]);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::at;
+use elevator::decision::{DecisionLog, QueueSnapshot, Reason};
+use elevator::strategies::scan::ScanStrategy;
+use elevator::transition::State;
+use elevator::types::event::Event;
+use std::cmp::Reverse;
+
+#[test]
+fn choices_carry_their_reason() {
+    let mut ctx = at(3, true);
+    ctx.up_queue.extend([Reverse(5), Reverse(4)]);
+    assert_eq!(ctx.choose_target(), Some((4, Reason::SameDirectionNearest)));
+
+    let mut ctx = at(3, true);
+    ctx.down_queue.push(1);
+    assert_eq!(ctx.choose_target(), Some((1, Reason::Reversed)));
+    assert!(!ctx.direction_up);
+
+    // Queued as an up call while the car was still below floor 2.
+    let mut ctx = at(3, true);
+    ctx.up_queue.push(Reverse(2));
+    assert_eq!(ctx.choose_target(), Some((2, Reason::Behind)));
+    assert!(!ctx.direction_up);
+
+    let mut ctx = at(3, true);
+    ctx.up_queue.push(Reverse(3));
+    assert_eq!(ctx.choose_target(), Some((3, Reason::CurrentFloor)));
+}
+
+#[test]
+fn scan_explains_the_target_it_picks() {
+    let mut ctx = at(3, true);
+    ctx.down_queue.push(2);
+    let handled = ScanStrategy::plan(Event::PanelButtonPressed(1), &mut ctx, State::Idle);
+
+    let decision = handled.decision.unwrap();
+    assert_eq!(decision.event, Event::PanelButtonPressed(1));
+    assert_eq!(decision.chosen, 2);
+    assert_eq!(decision.reason, Reason::Reversed);
+    assert_eq!(
+        decision.queues,
+        QueueSnapshot {
+            up: vec![],
+            down: vec![1, 2]
+        }
+    );
+    assert!(handled.plan.is_some());
+}
+
+#[test]
+fn busy_car_makes_no_decision() {
+    let mut ctx = at(3, true);
+    let handled = ScanStrategy::plan(Event::PanelButtonPressed(5), &mut ctx, State::MovingUp);
+    assert!(handled.decision.is_none());
+    assert!(handled.plan.is_none());
+}
+
+#[test]
+fn log_keeps_the_latest_decisions() {
+    let log = DecisionLog::new(2);
+    for floor in [2, 4, 5] {
+        let mut ctx = at(1, true);
+        let handled = ScanStrategy::plan(Event::PanelButtonPressed(floor), &mut ctx, State::Idle);
+        log.record(handled.decision.unwrap());
+    }
+    let chosen = |n| log.last(n).iter().map(|d| d.chosen).collect::<Vec<_>>();
+    assert_eq!(chosen(1), [5]);
+    assert_eq!(chosen(10), [4, 5]);
+}
+
+#[test]
+fn log_without_capacity_keeps_nothing() {
+    let log = DecisionLog::new(0);
+    let mut ctx = at(1, true);
+    let handled = ScanStrategy::plan(Event::PanelButtonPressed(3), &mut ctx, State::Idle);
+    log.record(handled.decision.unwrap());
+    assert!(log.last(10).is_empty());
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use common::Fixed;
+use elevator::car::{self, CarHandle};
+use elevator::config::ControllerConfig;
+use elevator::context::ElevatorContext;
+use elevator::error::{Anomaly, Error, ErrorClass};
+use elevator::metrics::Metrics;
+use elevator::services::scheduler::{MAX_RETRIES, RETRY_BACKOFF, SchedulerEventLayer};
+use elevator::transition::State;
+use elevator::types::event::Event;
+use elevator::types::plan::Plan;
+use elevator::types::sched_events::{Action, ScheduleEvent};
+use std::future::{Ready, ready};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower::{Layer, Service, ServiceExt};
+
+/// Fails its first `failures` calls with a transport error.
+#[derive(Clone)]
+struct Flaky {
+    failures: u32,
+    calls: Arc<AtomicU32>,
+}
+
+impl Service<Action> for Flaky {
+    type Response = ();
+    type Error = Error;
+    type Future = Ready<Result<(), Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _action: Action) -> Self::Future {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        ready(if call < self.failures {
+            Err(Error::Transport("connection refused".to_string()))
+        } else {
+            Ok(())
+        })
+    }
+}
+
+/// Runs a one-step plan against a controller failing `failures` times,
+/// returning the outcome and how many calls it took.
+async fn move_up(failures: u32) -> (Result<(), Error>, u32) {
+    let (tx, _) = common::loopback();
+    let car: CarHandle = car::spawn(State::Idle.enter(tx), ElevatorContext::new(1, 5));
+    let calls = Arc::new(AtomicU32::new(0));
+    let inner = Flaky {
+        failures,
+        calls: calls.clone(),
+    };
+    let mut plan = Plan::new();
+    plan.then(ScheduleEvent::Instant(Action::MovingUp));
+    let mut scheduler = SchedulerEventLayer::new(
+        Fixed(plan),
+        car,
+        ControllerConfig::default(),
+        Arc::new(Metrics::default()),
+    )
+    .layer(inner);
+    let result = scheduler
+        .ready()
+        .await
+        .unwrap()
+        .call(Event::PanelButtonPressed(3))
+        .await;
+    (result, calls.load(Ordering::SeqCst))
+}
+
+#[test]
+fn errors_are_classed_by_how_to_react() {
+    let cases = [
+        (Error::InvalidPacket("X9".to_string()), ErrorClass::Drop),
+        (Error::Transport("refused".to_string()), ErrorClass::Retry),
+        (
+            Error::GaveUp {
+                attempts: 4,
+                last: Box::new(Error::Transport("refused".to_string())),
+            },
+            ErrorClass::Fault,
+        ),
+        (
+            Error::Anomaly(Anomaly::UnexpectedStop(3)),
+            ErrorClass::Fault,
+        ),
+        (Error::CommandChannelClosed, ErrorClass::Escalate),
+        (Error::CarStopped, ErrorClass::Escalate),
+    ];
+    for (error, class) in cases {
+        assert_eq!(error.class(), class, "{error}");
+    }
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried_with_backoff() {
+    let started = Instant::now();
+    let (result, calls) = move_up(2).await;
+    assert!(result.is_ok());
+    assert_eq!(calls, 3);
+    // One backoff, then twice that.
+    assert!(started.elapsed() >= RETRY_BACKOFF * 3);
+}
+
+#[tokio::test]
+async fn retries_give_up_after_the_limit() {
+    let (result, calls) = move_up(u32::MAX).await;
+    let Err(Error::GaveUp { attempts, last }) = result else {
+        panic!("{result:?}");
+    };
+    assert_eq!(attempts, MAX_RETRIES + 1);
+    assert!(matches!(*last, Error::Transport(_)));
+    assert_eq!(calls, MAX_RETRIES + 1);
+}
@@ -0,0 +1,148 @@
+use elevator::error::{Anomaly, Error};
+use elevator::metrics::Metrics;
+use elevator::services::udp_event::UdpEventLayer;
+use elevator::types::event::Event;
+use proptest::prelude::*;
+use std::future::{Ready, ready};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tower::{Layer, Service, ServiceExt};
+
+/// Records every event and fails the stops.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Event>>>);
+
+impl Service<Event> for Recorder {
+    type Response = ();
+    type Error = Error;
+    type Future = Ready<Result<(), Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, event: Event) -> Self::Future {
+        self.0.lock().unwrap().push(event.clone());
+        ready(match event {
+            Event::ElevatorStopped(floor) => Err(Error::Anomaly(Anomaly::UnexpectedStop(floor))),
+            _ => Ok(()),
+        })
+    }
+}
+
+fn hardware_event() -> impl Strategy<Value = (String, Event)> {
+    (0..9usize, any::<u8>()).prop_map(|(kind, n)| {
+        let (code, event) = match kind {
+            0 => ('U', Event::ElevatorUp(n)),
+            1 => ('D', Event::ElevatorDown(n)),
+            2 => ('P', Event::PanelButtonPressed(n)),
+            3 => ('A', Event::ElevatorApproaching(n)),
+            4 => ('S', Event::ElevatorStopped(n)),
+            5 => ('O', Event::DoorOpened(n)),
+            6 => ('C', Event::DoorClosed(n)),
+            7 => ('K', Event::KeySwitched(n)),
+            _ => ('W', Event::LoadWeighed(n)),
+        };
+        (format!("{code}{n}"), event)
+    })
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_bytes_never_panic(data in proptest::collection::vec(any::<u8>(), 0..64)) {
+        let _ = Event::parse_datagram(&data);
+    }
+
+    #[test]
+    fn every_event_in_a_datagram_is_returned(
+        events in proptest::collection::vec(hardware_event(), 1..8),
+        crlf in any::<bool>(),
+    ) {
+        let separator = if crlf { "\r\n" } else { "\n" };
+        let datagram = events
+            .iter()
+            .map(|(wire, _)| format!("{wire}{separator}"))
+            .collect::<String>();
+        let parsed: Vec<_> = Event::parse_datagram(datagram.as_bytes())
+            .into_iter()
+            .map(|e| e.unwrap())
+            .collect();
+        let expected: Vec<_> = events.into_iter().map(|(_, e)| e).collect();
+        prop_assert_eq!(parsed, expected);
+    }
+}
+
+#[test]
+fn bad_lines_do_not_hide_good_ones() {
+    let parsed = Event::parse_datagram(b"A2\nU300\nX1\n\nS2 \r\n-1\nP+3");
+    let ok: Vec<_> = parsed.iter().filter_map(|e| e.as_ref().ok()).collect();
+    assert_eq!(
+        ok,
+        [&Event::ElevatorApproaching(2), &Event::ElevatorStopped(2)]
+    );
+    assert_eq!(parsed.len(), 6);
+}
+
+#[tokio::test]
+async fn floors_outside_the_building_are_rejected() {
+    // The codec reads any floor; the building's range comes with the layer.
+    assert_eq!(
+        Event::try_from(b"U9".as_slice()).unwrap(),
+        Event::ElevatorUp(9)
+    );
+    let datagram = b"U0\nU9\nD6\nP0\nA6\nO200\nC6\nP5\nW120".as_slice();
+    let recorder = Recorder::default();
+    let mut service =
+        UdpEventLayer::new(Arc::new(Metrics::default()), 1..=5).layer(recorder.clone());
+    let result = ServiceExt::<&[u8]>::ready(&mut service)
+        .await
+        .unwrap()
+        .call(datagram)
+        .await;
+    assert!(matches!(result, Err(Error::InvalidPacket(_))));
+    // Key positions and loads are not floors.
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        [Event::PanelButtonPressed(5), Event::LoadWeighed(120)]
+    );
+
+    let recorder = Recorder::default();
+    let mut service =
+        UdpEventLayer::new(Arc::new(Metrics::default()), 1..=9).layer(recorder.clone());
+    ServiceExt::<&[u8]>::ready(&mut service)
+        .await
+        .unwrap()
+        .call(b"U9\nD6".as_slice())
+        .await
+        .unwrap();
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        [Event::ElevatorUp(9), Event::ElevatorDown(6)]
+    );
+}
+
+#[tokio::test]
+async fn a_failed_event_does_not_drop_the_rest_of_the_datagram() {
+    let recorder = Recorder::default();
+    let mut service =
+        UdpEventLayer::new(Arc::new(Metrics::default()), 1..=5).layer(recorder.clone());
+    let result = ServiceExt::<&[u8]>::ready(&mut service)
+        .await
+        .unwrap()
+        .call(b"X1\nS2\nO2\nU9\nP4".as_slice())
+        .await;
+
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        [
+            Event::ElevatorStopped(2),
+            Event::DoorOpened(2),
+            Event::PanelButtonPressed(4)
+        ]
+    );
+    // The failed event outranks the invalid lines around it.
+    assert!(matches!(
+        result,
+        Err(Error::Anomaly(Anomaly::UnexpectedStop(2)))
+    ));
+}
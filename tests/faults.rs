@@ -0,0 +1,111 @@
+mod common;
+
+use common::{Sent, at};
+use elevator::car::{self, CarHandle};
+use elevator::config::{ControllerConfig, FaultPolicy};
+use elevator::context::{Call, ElevatorContext};
+use elevator::error::{Anomaly, Error};
+use elevator::strategies::scan::ScanStrategy;
+use elevator::transition::State;
+use elevator::types::cmd::Command;
+use elevator::types::event::Event;
+use std::collections::BTreeSet;
+use tower::{Service, ServiceExt};
+
+/// Feeds `event` to a strict controller with `policy`, returning the error
+/// the car and what it sent.
+async fn fault(
+    state: State,
+    context: ElevatorContext,
+    event: Event,
+    policy: FaultPolicy,
+) -> (Error, CarHandle, Sent) {
+    let (tx, sent) = common::loopback();
+    let car = car::spawn(state.enter(tx), context);
+    let config = ControllerConfig {
+        strict: true,
+        fault_policy: policy,
+    };
+    let (mut scheduler, metrics) = common::scheduler(ScanStrategy::new(), &car, config);
+    let error = scheduler
+        .ready()
+        .await
+        .unwrap()
+        .call(event)
+        .await
+        .unwrap_err();
+    assert!(
+        metrics
+            .render(&ElevatorContext::new(1, 5))
+            .contains("elevator_faults_total 1")
+    );
+    (error, car, sent)
+}
+
+/// A car on its way up from floor 1 to floor 4, with a call for 5 queued.
+fn moving_up() -> ElevatorContext {
+    let mut ctx = at(1, true);
+    ctx.enqueue_call(4, Call::Car);
+    ctx.enqueue_call(5, Call::HallDown);
+    assert_eq!(ctx.next_target(), Some(4));
+    ctx.depart(true);
+    ctx
+}
+
+fn owed() -> BTreeSet<(u8, Call)> {
+    BTreeSet::from([(4, Call::Car), (5, Call::HallDown)])
+}
+
+#[tokio::test]
+async fn alert_only_reports_and_keeps_the_calls() {
+    let mut ctx = at(1, true);
+    ctx.enqueue_call(4, Call::Car);
+    let (error, car, sent) =
+        fault(State::Idle, ctx, Event::DoorClosed(1), FaultPolicy::Alert).await;
+    assert!(matches!(
+        error,
+        Error::Anomaly(Anomaly::UnexpectedDoorClosed(1))
+    ));
+
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.state, State::Idle);
+    assert!(sent.lock().unwrap().is_empty());
+    // The call was not taken as a target for a move that never ran.
+    assert_eq!(snapshot.context.active_target, None);
+    assert_eq!(snapshot.context.pending(), BTreeSet::from([(4, Call::Car)]));
+}
+
+#[tokio::test]
+async fn stop_safely_holds_the_car_with_its_calls() {
+    let (error, car, sent) = fault(
+        State::MovingUp,
+        moving_up(),
+        Event::ElevatorStopped(2),
+        FaultPolicy::StopSafely,
+    )
+    .await;
+    assert!(matches!(error, Error::Anomaly(Anomaly::UnexpectedStop(2))));
+
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.state, State::EmergencyBrake);
+    assert_eq!(*sent.lock().unwrap(), [Command::S]);
+    assert_eq!(snapshot.context.pending(), owed());
+}
+
+#[tokio::test]
+async fn reset_keeps_the_calls_and_sets_off_again() {
+    let (error, car, sent) = fault(
+        State::MovingUp,
+        moving_up(),
+        Event::ElevatorStopped(2),
+        FaultPolicy::Reset,
+    )
+    .await;
+    assert!(matches!(error, Error::Anomaly(Anomaly::UnexpectedStop(2))));
+
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(*sent.lock().unwrap(), [Command::R, Command::MU]);
+    assert_eq!(snapshot.state, State::MovingUp);
+    assert_eq!(snapshot.context.active_target, Some(4));
+    assert_eq!(snapshot.context.pending(), owed());
+}
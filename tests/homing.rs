@@ -0,0 +1,92 @@
+mod common;
+
+use common::{Sent, queued};
+use elevator::car::{self, CarHandle};
+use elevator::config::{ControllerConfig, Startup};
+use elevator::context::{Call, ElevatorContext};
+use elevator::position::Position;
+use elevator::strategies::scan::ScanStrategy;
+use elevator::transition::{ElevatorState, PreStart, State};
+use elevator::types::cmd::Command;
+use elevator::types::event::Event;
+use tower::{Service, ServiceExt};
+
+async fn home_down() -> (CarHandle, Sent) {
+    let (tx, sent) = common::loopback();
+    let car = car::start(
+        ElevatorState::<PreStart>::new(tx),
+        ElevatorContext::new(1, 5),
+        Startup::Home { up: false },
+    );
+    assert_eq!(car.snapshot().await.unwrap().state, State::Homing);
+    (car, sent)
+}
+
+async fn feed(car: &CarHandle, events: impl IntoIterator<Item = Event>) {
+    let (mut scheduler, _) =
+        common::scheduler(ScanStrategy::new(), car, ControllerConfig::default());
+    for event in events {
+        scheduler.ready().await.unwrap().call(event).await.unwrap();
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn homing_finds_the_floor_from_the_first_approach() {
+    let (car, sent) = home_down().await;
+    feed(
+        &car,
+        [Event::ElevatorApproaching(3), Event::ElevatorStopped(3)],
+    )
+    .await;
+
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.context.position, Position::Stopped(3));
+    assert_eq!(
+        snapshot.state,
+        State::Idle,
+        "nobody called, the door stays shut"
+    );
+    assert_eq!(
+        *sent.lock().unwrap(),
+        [Command::MD, Command::S],
+        "recall down, then stop at the first floor"
+    );
+    assert!(!car::reset_if_lost(&car).await.unwrap());
+}
+
+#[tokio::test]
+async fn calls_wait_until_homing_found_the_floor() {
+    let (car, sent) = home_down().await;
+    feed(&car, [Event::PanelButtonPressed(4), Event::ElevatorUp(3)]).await;
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(queued(&snapshot.context), (vec![], vec![]));
+    assert_eq!(snapshot.context.active_target, None);
+
+    feed(
+        &car,
+        [Event::ElevatorApproaching(2), Event::ElevatorStopped(2)],
+    )
+    .await;
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.state, State::MovingUp);
+    assert_eq!(snapshot.context.active_target, Some(3));
+    assert_eq!(queued(&snapshot.context), (vec![4], vec![]));
+    assert_eq!(
+        *sent.lock().unwrap(),
+        [Command::MD, Command::S, Command::MU]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn homing_past_the_end_of_the_shaft_resets() {
+    // Recalled down from floor 1, Lifty hits the ground and never approaches.
+    let (car, sent) = home_down().await;
+    feed(&car, [Event::PanelButtonPressed(4)]).await;
+
+    assert!(car::reset_if_lost(&car).await.unwrap());
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.state, State::Idle);
+    assert_eq!(snapshot.context.position, Position::Stopped(1));
+    assert_eq!(snapshot.context.pending(), [(4, Call::Car)].into());
+    assert_eq!(*sent.lock().unwrap(), [Command::MD, Command::R]);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::brakes;
+use elevator::context::ElevatorContext;
+use elevator::decision::Reason;
+use elevator::strategies::scan::ScanStrategy;
+use elevator::transition::State;
+use elevator::types::event::Event;
+
+/// A car that left floor 1 for a call on floor 5.
+fn on_the_way_up() -> ElevatorContext {
+    let mut ctx = ElevatorContext::new(1, 5);
+    let handled = ScanStrategy::plan(Event::PanelButtonPressed(5), &mut ctx, State::Idle);
+    assert!(handled.plan.is_some());
+    ctx.depart(true);
+    ctx
+}
+
+#[test]
+fn early_call_for_the_next_floor_stops_the_car_there() {
+    let mut ctx = on_the_way_up();
+    assert!(!brakes(Event::PanelButtonPressed(2), &mut ctx));
+    assert_eq!(ctx.active_target, Some(2));
+    assert!(ctx.can_stop_at(2));
+
+    assert!(brakes(Event::ElevatorApproaching(2), &mut ctx));
+    // The original call is still served afterwards.
+    assert_eq!(ctx.up_queue.peek().map(|r| r.0), Some(5));
+}
+
+#[test]
+fn late_call_for_the_next_floor_waits_for_the_way_back() {
+    let mut ctx = on_the_way_up();
+    assert!(!brakes(Event::ElevatorApproaching(2), &mut ctx));
+    assert!(!ctx.can_stop_at(2) && ctx.can_stop_at(3));
+
+    assert!(!brakes(Event::PanelButtonPressed(2), &mut ctx));
+    assert_eq!(ctx.active_target, Some(5));
+    assert_eq!(ctx.down_queue.peek(), Some(&2));
+    assert!(!brakes(Event::ElevatorApproaching(3), &mut ctx));
+}
+
+#[test]
+fn calls_ahead_stop_the_car_on_the_approach() {
+    let mut ctx = on_the_way_up();
+    assert!(!brakes(Event::PanelButtonPressed(3), &mut ctx));
+    ctx.down_queue.push(4);
+    assert!(!brakes(Event::ElevatorApproaching(2), &mut ctx));
+
+    let handled = ScanStrategy::plan(Event::ElevatorApproaching(3), &mut ctx, State::MovingUp);
+    let decision = handled.decision.unwrap();
+    assert_eq!((decision.chosen, decision.reason), (3, Reason::OnTheWay));
+    assert_eq!(ctx.active_target, Some(3));
+    assert_eq!(ctx.up_queue.peek().map(|r| r.0), Some(5));
+}
+
+#[test]
+fn calls_for_the_other_direction_are_passed() {
+    let mut ctx = on_the_way_up();
+    ctx.down_queue.push(3);
+    assert!(!brakes(Event::ElevatorApproaching(2), &mut ctx));
+    assert!(!brakes(Event::ElevatorApproaching(3), &mut ctx));
+    assert_eq!(ctx.active_target, Some(5));
+    assert_eq!(ctx.down_queue.peek(), Some(&3));
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use common::{at, brakes, queued};
+use elevator::car;
+use elevator::config::ControllerConfig;
+use elevator::context::{Call, ElevatorContext};
+use elevator::metrics::Metrics;
+use elevator::services::controller::ControllerService;
+use elevator::services::udp_event::UdpEventLayer;
+use elevator::strategies::scan::ScanStrategy;
+use elevator::transition::State;
+use elevator::types::cmd::Command;
+use elevator::types::event::Event;
+use elevator::types::plan::Precondition;
+use elevator::types::sched_events::{Action, ScheduleEvent};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::{Layer, Service, ServiceExt};
+
+/// A car that left floor 1 for a hall call on floor 3 carrying `load`
+/// percent, with a rider for floor 5.
+fn loaded_car(load: u8) -> ElevatorContext {
+    let mut ctx = ElevatorContext::new(1, 5);
+    ScanStrategy::plan(Event::ElevatorUp(3), &mut ctx, State::Idle);
+    ctx.depart(true);
+    ctx.enqueue_call(5, Call::Car);
+    ScanStrategy::plan(Event::LoadWeighed(load), &mut ctx, State::MovingUp);
+    ScanStrategy::plan(Event::ElevatorApproaching(2), &mut ctx, State::MovingUp);
+    ctx
+}
+
+#[test]
+fn near_full_car_passes_hall_calls() {
+    let mut ctx = loaded_car(85);
+    assert_eq!(ctx.load, 85);
+    assert!(!brakes(Event::ElevatorApproaching(3), &mut ctx));
+    assert_eq!(ctx.active_target, Some(5));
+    // The up call waits for the next sweep up, still an up call.
+    assert_eq!(queued(&ctx), (vec![3], vec![]));
+    assert!(ctx.calls.contains(&(3, Call::HallUp)));
+    assert!(!brakes(Event::ElevatorApproaching(4), &mut ctx));
+    assert!(brakes(Event::ElevatorApproaching(5), &mut ctx));
+}
+
+#[test]
+fn car_with_room_stops_for_hall_calls() {
+    let mut ctx = loaded_car(50);
+    assert!(brakes(Event::ElevatorApproaching(3), &mut ctx));
+}
+
+#[test]
+fn near_full_car_with_riders_for_the_floor_stops() {
+    let mut ctx = loaded_car(85);
+    ctx.enqueue_call(3, Call::Car);
+    assert!(brakes(Event::ElevatorApproaching(3), &mut ctx));
+}
+
+#[test]
+fn near_full_car_with_nowhere_else_to_go_stops() {
+    let mut ctx = loaded_car(85);
+    ctx.cancel_request(5);
+    assert!(brakes(Event::ElevatorApproaching(3), &mut ctx));
+}
+
+#[tokio::test]
+async fn overloaded_car_keeps_its_door_open() {
+    let (tx, sent) = common::loopback();
+    let mut context = at(3, true);
+    context.load = 120;
+    context.active_target = Some(3);
+    let car = car::spawn(State::DoorOpened.enter(tx), context);
+
+    let applied = car.apply(Action::HoldingDoor, true).await.unwrap();
+    assert!(applied.legal);
+    assert_eq!(applied.result.unwrap(), State::DoorOpened);
+    assert!(sent.lock().unwrap().is_empty());
+
+    // The close planned when the door opened is skipped while overloaded.
+    let mut ctx = car.snapshot().await.unwrap().context;
+    let plan = ScanStrategy::plan(Event::DoorOpened(3), &mut ctx, State::DoorOpening)
+        .plan
+        .unwrap();
+    let close = plan
+        .steps()
+        .find(|step| matches!(step.event, ScheduleEvent::WaitTime(_, Action::ClosingDoor)))
+        .unwrap();
+    assert_eq!(
+        close.violated(State::DoorOpened, &ctx),
+        Some(Precondition::WithinCapacity)
+    );
+    assert!(
+        plan.steps()
+            .any(|step| matches!(step.event, ScheduleEvent::Instant(Action::HoldingDoor)))
+    );
+
+    // Once someone steps off, the close is tried again.
+    let handled = ScanStrategy::plan(Event::LoadWeighed(95), &mut ctx, State::DoorOpened);
+    let plan = handled.plan.unwrap();
+    let close = plan.steps().next().unwrap();
+    assert!(matches!(
+        close.event,
+        ScheduleEvent::WaitTime(_, Action::ClosingDoor)
+    ));
+    assert_eq!(close.violated(State::DoorOpened, &ctx), None);
+}
+
+#[tokio::test]
+async fn holding_the_door_is_not_an_ignored_action() {
+    let (tx, _) = common::loopback();
+    let mut context = at(3, true);
+    context.load = 120;
+    let car = car::spawn(State::DoorOpened.enter(tx), context);
+    let metrics = Arc::new(Metrics::default());
+    let mut controller = ControllerService::new(car, metrics.clone(), true);
+    controller.transport_up();
+
+    controller
+        .ready()
+        .await
+        .unwrap()
+        .call(Action::HoldingDoor)
+        .await
+        .unwrap();
+    let rendered = metrics.render(&ElevatorContext::new(1, 5));
+    assert!(!rendered.contains("elevator_ignored_actions_total{"));
+    assert!(
+        rendered
+            .contains("elevator_state_transitions_total{from=\"DoorOpened\",to=\"DoorOpened\"} 1")
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn weighing_during_the_door_wait_holds_the_door() {
+    let (tx, sent) = common::loopback();
+    let mut context = at(3, true);
+    context.active_target = Some(3);
+    let car = car::spawn(State::DoorOpening.enter(tx), context);
+    let (scheduler, _) = common::scheduler(ScanStrategy::new(), &car, ControllerConfig::default());
+    let mut svc = UdpEventLayer::new(Arc::new(Metrics::default()), 1..=5).layer(scheduler);
+    let mut receive = async |datagram: &str| {
+        ServiceExt::<&[u8]>::ready(&mut svc)
+            .await
+            .unwrap()
+            .call(datagram.as_bytes())
+            .await
+            .unwrap();
+    };
+
+    receive("O3").await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    receive("W120").await;
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(
+        snapshot.state,
+        State::DoorOpened,
+        "the close came due overloaded"
+    );
+    assert!(!sent.lock().unwrap().contains(&Command::DC));
+
+    receive("W60").await;
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert_eq!(car.snapshot().await.unwrap().state, State::DoorClosing);
+    assert_eq!(sent.lock().unwrap().last(), Some(&Command::DC));
+}
+
+#[test]
+fn near_full_car_heads_for_its_riders_first() {
+    let mut ctx = ElevatorContext::new(1, 5);
+    ctx.load = 90;
+    ctx.enqueue_call(1, Call::HallUp);
+    ctx.enqueue_call(2, Call::HallUp);
+    ctx.enqueue_call(4, Call::Car);
+    assert_eq!(ctx.next_target(), Some(4));
+    ctx.load = 40;
+    assert_eq!(ctx.next_target(), Some(1));
+}
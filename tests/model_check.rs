@@ -0,0 +1,10 @@
+use elevator::model_check::explore;
+
+#[test]
+fn controller_never_crashes_or_strands_a_call() {
+    let report = explore(1, 5, 12, 2);
+    if let Some(counterexample) = report.counterexample {
+        panic!("{counterexample}");
+    }
+    assert!(report.states > 1);
+}
@@ -0,0 +1,135 @@
+use elevator::lifty::{Door, Elevator, MAX_FLOOR};
+use elevator::passengers::{LOBBY, Passengers, Traffic};
+use elevator::types::event::Event;
+
+const TEN_MINUTES: u64 = 6_000;
+
+/// Ten minutes of arrivals with the car parked, doors shut.
+fn ten_minutes_of(traffic: Traffic) -> Passengers {
+    let elevator = Elevator::new();
+    let mut passengers = Passengers::new(traffic, 30.0, 7);
+    for _ in 0..TEN_MINUTES {
+        passengers.tick(&elevator);
+    }
+    passengers
+}
+
+#[test]
+fn arrivals_follow_the_rate() {
+    let arrived = ten_minutes_of(Traffic::Poisson).waiting.len();
+    assert!((240..=360).contains(&arrived), "{arrived} arrivals");
+}
+
+#[test]
+fn unbounded_rates_still_end_each_tick() {
+    let elevator = Elevator::new();
+    let mut flood = Passengers::new(Traffic::Poisson, f64::INFINITY, 7);
+    let mut nonsense = Passengers::new(Traffic::Poisson, f64::NAN, 7);
+    for _ in 0..10 {
+        flood.tick(&elevator);
+        nonsense.tick(&elevator);
+    }
+    assert_eq!(flood.waiting.len(), 10, "one arrival a tick at most");
+    assert!(nonsense.waiting.is_empty());
+}
+
+#[test]
+fn traffic_patterns_pick_their_floors() {
+    let waiting = ten_minutes_of(Traffic::Poisson).waiting;
+    assert!(waiting.iter().all(|p| p.origin != p.destination));
+    assert!(waiting.iter().any(|p| p.origin == MAX_FLOOR));
+
+    let up_peak = ten_minutes_of(Traffic::UpPeak).waiting;
+    assert!(
+        up_peak
+            .iter()
+            .all(|p| p.origin == LOBBY && p.destination > LOBBY)
+    );
+
+    let interfloor = ten_minutes_of(Traffic::Interfloor).waiting;
+    assert!(
+        interfloor
+            .iter()
+            .all(|p| p.origin != LOBBY && p.destination != LOBBY && p.origin != p.destination)
+    );
+}
+
+#[test]
+fn passengers_board_ride_and_alight() {
+    let mut elevator = Elevator::new();
+    let mut passengers = Passengers::new(Traffic::Poisson, 0.0, 1);
+    passengers.arrive(1, 3, 10);
+    passengers.arrive(1, 4, 10);
+    assert_eq!(passengers.tick(&elevator), [Event::ElevatorUp(1)]);
+    // A lit button is not pressed again.
+    elevator.up_buttons[0] = true;
+    assert!(passengers.tick(&elevator).is_empty());
+
+    elevator.up_buttons[0] = false;
+    elevator.door = Door::Open;
+    assert_eq!(
+        passengers.tick(&elevator),
+        [
+            Event::LoadWeighed(20),
+            Event::PanelButtonPressed(3),
+            Event::PanelButtonPressed(4)
+        ]
+    );
+    assert_eq!(passengers.riding.len(), 2);
+
+    elevator.floor = 3;
+    for _ in 0..10 {
+        passengers.tick(&elevator);
+    }
+    let delivered = &passengers.delivered;
+    assert_eq!(delivered.len(), 1);
+    assert_eq!(delivered[0].waiting_ticks(), Some(3));
+    assert_eq!(delivered[0].journey_ticks(), Some(4));
+    let summary = passengers.summary();
+    assert_eq!((summary.delivered, summary.riding), (1, 1));
+    assert!((summary.mean_wait_secs - 0.3).abs() < 1e-9);
+}
+
+#[test]
+fn passengers_wait_for_a_car_going_their_way() {
+    let mut elevator = Elevator::new();
+    let mut passengers = Passengers::new(Traffic::Poisson, 0.0, 1);
+    passengers.arrive(3, 5, 10);
+    passengers.arrive(3, 1, 10);
+    passengers.arrive(4, 2, 10);
+    elevator.floor = 4;
+    elevator.door = Door::Open;
+    passengers.tick(&elevator);
+    assert_eq!(passengers.riding.len(), 1, "an empty car takes anyone");
+
+    // Heading down from the top, with someone aboard.
+    elevator.floor = 5;
+    elevator.door = Door::Closed;
+    passengers.tick(&elevator);
+    elevator.floor = 3;
+    elevator.door = Door::Open;
+    passengers.tick(&elevator);
+    let riding: Vec<_> = passengers.riding.iter().map(|p| p.destination).collect();
+    assert_eq!(riding, [2, 1]);
+    assert_eq!(passengers.waiting[0].destination, 5);
+}
+
+#[test]
+fn overloaded_car_sheds_its_last_passenger() {
+    let mut elevator = Elevator::new();
+    let mut passengers = Passengers::new(Traffic::Poisson, 0.0, 1);
+    for _ in 0..3 {
+        passengers.arrive(1, 5, 45);
+    }
+    elevator.door = Door::Open;
+    assert_eq!(passengers.tick(&elevator)[0], Event::LoadWeighed(135));
+    assert_eq!(passengers.riding.len(), 3, "the last one squeezes in");
+
+    elevator.load = 135;
+    elevator.clock = 100;
+    assert_eq!(passengers.tick(&elevator)[0], Event::LoadWeighed(90));
+    assert_eq!(passengers.waiting.len(), 1);
+    elevator.load = 90;
+    passengers.tick(&elevator);
+    assert_eq!(passengers.riding.len(), 2, "and waits for the next car");
+}
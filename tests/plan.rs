@@ -0,0 +1,126 @@
+mod common;
+
+use common::{Fixed, at, queued};
+use elevator::car;
+use elevator::config::ControllerConfig;
+use elevator::context::{Call, ElevatorContext};
+use elevator::error::Error;
+use elevator::metrics::Metrics;
+use elevator::services::scheduler::SchedulerEventLayer;
+use elevator::strategies::scan::ScanStrategy;
+use elevator::transition::State;
+use elevator::types::cmd::Command;
+use elevator::types::event::Event;
+use elevator::types::plan::{Plan, PlanReport, Precondition, StepOutcome};
+use elevator::types::sched_events::{Action, ScheduleEvent};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::{Layer, Service, ServiceExt};
+
+#[tokio::test]
+async fn stale_steps_are_skipped_and_reported() {
+    let (tx, sent) = common::loopback();
+    let car = car::spawn(State::Idle.enter(tx), ElevatorContext::new(1, 5));
+
+    let mut plan = Plan::new();
+    plan.then(ScheduleEvent::Instant(Action::MovingUp))
+        .then_if(
+            ScheduleEvent::Instant(Action::OpeningDoor),
+            [Precondition::InState(State::Idle)],
+        )
+        .then_if(
+            ScheduleEvent::Instant(Action::Braking),
+            [Precondition::InState(State::MovingUp)],
+        );
+    let (mut scheduler, metrics) =
+        common::scheduler(Fixed(plan), &car, ControllerConfig::default());
+
+    scheduler
+        .ready()
+        .await
+        .unwrap()
+        .call(Event::PanelButtonPressed(3))
+        .await
+        .unwrap();
+
+    assert_eq!(*sent.lock().unwrap(), [Command::MU, Command::S]);
+    assert_eq!(car.snapshot().await.unwrap().state, State::Braking);
+    let rendered = metrics.render(&ElevatorContext::new(1, 5));
+    assert!(rendered.contains("elevator_plan_steps_total{outcome=\"executed\"} 2"));
+    assert!(rendered.contains("elevator_plan_steps_total{outcome=\"skipped\"} 1"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_newer_event_makes_a_waiting_step_stale() {
+    let (tx, sent) = common::loopback();
+    let mut context = at(3, true);
+    context.active_target = Some(3);
+    let car = car::spawn(State::DoorOpening.enter(tx), context);
+    let (mut scheduler, metrics) =
+        common::scheduler(ScanStrategy::new(), &car, ControllerConfig::default());
+
+    // The door closes 2s after it opened, unless something came up meanwhile.
+    for event in [Event::DoorOpened(3), Event::EmergencyStop] {
+        scheduler.ready().await.unwrap().call(event).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    assert_eq!(car.snapshot().await.unwrap().state, State::EmergencyBrake);
+    assert!(!sent.lock().unwrap().contains(&Command::DC));
+    let rendered = metrics.render(&ElevatorContext::new(1, 5));
+    assert!(rendered.contains("elevator_plan_steps_total{outcome=\"skipped\"} 1"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn abandoned_move_puts_its_target_back() {
+    let (tx, _) = common::loopback();
+    let mut context = at(3, true);
+    context.enqueue_call(5, Call::HallDown);
+    let car = car::spawn(State::DoorClosing.enter(tx), context);
+    // The door closed, but telling the car so keeps failing.
+    let inner = tower::service_fn(|action: Action| async move {
+        match action {
+            Action::DoorClosed => Err(Error::Transport("refused".to_string())),
+            _ => Ok(()),
+        }
+    });
+    let mut scheduler = SchedulerEventLayer::new(
+        ScanStrategy::new(),
+        car.clone(),
+        ControllerConfig::default(),
+        Arc::new(Metrics::default()),
+    )
+    .layer(inner);
+
+    let result = scheduler
+        .ready()
+        .await
+        .unwrap()
+        .call(Event::DoorClosed(3))
+        .await;
+    assert!(matches!(result, Err(Error::GaveUp { .. })));
+
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.context.active_target, None);
+    assert_eq!(queued(&snapshot.context), (vec![], vec![5]));
+}
+
+#[test]
+fn report_lists_executed_and_skipped_steps() {
+    let mut report = PlanReport::default();
+    report.record(Action::MovingUp, StepOutcome::Executed);
+    report.record(
+        Action::OpeningDoor,
+        StepOutcome::Skipped(Precondition::InState(State::Idle)),
+    );
+    report.record(Action::Braking, StepOutcome::Failed);
+    report.record(Action::Stopped, StepOutcome::Abandoned);
+
+    assert_eq!(report.executed().collect::<Vec<_>>(), [&Action::MovingUp]);
+    assert_eq!(report.skipped().collect::<Vec<_>>(), [&Action::OpeningDoor]);
+    assert_eq!(
+        report.to_string(),
+        "plan: MovingUp=executed OpeningDoor=skipped(InState(Idle)) Braking=failed Stopped=abandoned"
+    );
+}
@@ -0,0 +1,121 @@
+use elevator::context::{ElevatorContext, Location};
+use elevator::position::{Direction, Phase, Position};
+
+const UP: Direction = Direction::Up;
+const DOWN: Direction = Direction::Down;
+
+#[test]
+fn positions_order_along_the_shaft() {
+    let trip = [
+        Position::Stopped(2),
+        Position::Departing {
+            from: 2,
+            direction: UP,
+        },
+        Position::Approaching {
+            floor: 3,
+            direction: UP,
+        },
+        Position::Stopped(3),
+    ];
+    assert!(trip.windows(2).all(|pair| pair[0] < pair[1]), "{trip:?}");
+
+    // Same half floor: the car further into its move sorts later.
+    let departing = Position::Departing {
+        from: 2,
+        direction: UP,
+    };
+    let approaching = Position::Approaching {
+        floor: 3,
+        direction: UP,
+    };
+    let levelling = Position::Levelling {
+        floor: 3,
+        direction: UP,
+    };
+    assert!(departing < approaching && approaching < levelling);
+    assert_eq!(departing.location(), levelling.location());
+    assert_eq!(approaching.phase(), Phase::Approaching);
+}
+
+#[test]
+fn locations_follow_the_position() {
+    assert_eq!(Position::Stopped(4).location(), Location::AtFloor(4));
+    let down = Position::Approaching {
+        floor: 2,
+        direction: DOWN,
+    };
+    assert_eq!(down.location(), Location::BetweenFloors(2, 3));
+    assert!(Location::AtFloor(2) < Location::BetweenFloors(2, 3));
+    assert!(Location::BetweenFloors(2, 3) < Location::AtFloor(3));
+}
+
+#[test]
+fn stopping_is_only_possible_ahead() {
+    let departing = Position::Departing {
+        from: 2,
+        direction: UP,
+    };
+    assert!(!departing.can_stop_at(2));
+    assert!(departing.can_stop_at(3) && departing.can_stop_at(5));
+
+    let approaching = Position::Approaching {
+        floor: 3,
+        direction: DOWN,
+    };
+    assert!(approaching.can_stop_at(3) && approaching.can_stop_at(1));
+    assert!(!approaching.can_stop_at(4));
+
+    let levelling = Position::Levelling {
+        floor: 3,
+        direction: DOWN,
+    };
+    assert!(levelling.can_stop_at(3) && !levelling.can_stop_at(2));
+    assert!(Position::Stopped(3).can_stop_at(3) && !Position::Stopped(3).can_stop_at(4));
+}
+
+#[test]
+fn hardware_events_move_the_position() {
+    let mut ctx = ElevatorContext::new(1, 5);
+    ctx.depart(true);
+    assert_eq!(
+        ctx.position,
+        Position::Departing {
+            from: 1,
+            direction: UP
+        }
+    );
+    ctx.approach(2);
+    ctx.approach(3);
+    ctx.level();
+    assert_eq!(
+        ctx.position,
+        Position::Levelling {
+            floor: 3,
+            direction: UP
+        }
+    );
+    ctx.stopped_at(3);
+    assert_eq!(ctx.position, Position::Stopped(3));
+}
+
+#[test]
+fn hardware_wins_over_the_model() {
+    let mut ctx = ElevatorContext::new(1, 5);
+    ctx.depart(true);
+    // The approach to floor 2 was lost.
+    ctx.approach(3);
+    assert_eq!(
+        ctx.position,
+        Position::Approaching {
+            floor: 3,
+            direction: UP
+        }
+    );
+    // Approaches off the building or against the terminal are ignored.
+    ctx.approach(9);
+    ctx.approach(1);
+    assert_eq!(ctx.position.next_floor(), Some(3));
+    ctx.stopped_at(4);
+    assert_eq!(ctx.position, Position::Stopped(4));
+}
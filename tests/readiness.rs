@@ -0,0 +1,84 @@
+use elevator::car;
+use elevator::command_channel::{self, COMMAND_CHANNEL_CAPACITY};
+use elevator::config::ControllerConfig;
+use elevator::context::ElevatorContext;
+use elevator::metrics::Metrics;
+use elevator::services::controller::ControllerService;
+use elevator::services::scheduler::SchedulerEventLayer;
+use elevator::services::udp_event::UdpEventLayer;
+use elevator::strategies::scan::ScanStrategy;
+use elevator::transition::State;
+use elevator::types::cmd::Command;
+use elevator::types::event::Event;
+use elevator::types::sched_events::Action;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+use tower::{Service, ServiceBuilder, ServiceExt};
+
+const PENDING: Duration = Duration::from_millis(50);
+const READY: Duration = Duration::from_secs(1);
+
+#[tokio::test]
+async fn controller_waits_for_transport_and_for_the_running_transition() {
+    let (tx, mut rx) = command_channel::channel(COMMAND_CHANNEL_CAPACITY);
+    let car = car::spawn(State::Idle.enter(tx), ElevatorContext::new(1, 5));
+    let mut controller = ControllerService::new(car, Arc::new(Metrics::default()), false);
+    assert!(timeout(PENDING, controller.ready()).await.is_err());
+
+    controller.transport_up();
+    let call = timeout(READY, controller.ready())
+        .await
+        .unwrap()
+        .unwrap()
+        .call(Action::MovingUp);
+    let call = tokio::spawn(call);
+
+    // The transition is waiting for MU to be written.
+    let pending = rx.recv().await.unwrap();
+    assert_eq!(pending.batch, [Command::MU]);
+    assert!(timeout(PENDING, controller.ready()).await.is_err());
+
+    pending.confirm(Ok(()));
+    call.await.unwrap().unwrap();
+    timeout(READY, controller.ready()).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn the_whole_stack_waits_for_the_controller() {
+    let (tx, mut rx) = command_channel::channel(COMMAND_CHANNEL_CAPACITY);
+    let car = car::spawn(State::Idle.enter(tx), ElevatorContext::new(1, 5));
+    let metrics = Arc::new(Metrics::default());
+    let controller = ControllerService::new(car.clone(), metrics.clone(), false);
+    controller.transport_up();
+    let mut svc = ServiceBuilder::new()
+        .layer(UdpEventLayer::new(metrics.clone(), 1..=5))
+        .layer(SchedulerEventLayer::new(
+            ScanStrategy::new(),
+            car,
+            ControllerConfig::default(),
+            metrics,
+        ))
+        .service(controller);
+
+    timeout(READY, ServiceExt::<Event>::ready(&mut svc))
+        .await
+        .unwrap()
+        .unwrap();
+    let call = tokio::spawn(svc.call(Event::PanelButtonPressed(3)));
+
+    let pending = rx.recv().await.unwrap();
+    assert_eq!(pending.batch, [Command::MU]);
+    assert!(
+        timeout(PENDING, ServiceExt::<&[u8]>::ready(&mut svc))
+            .await
+            .is_err()
+    );
+
+    pending.confirm(Ok(()));
+    call.await.unwrap().unwrap();
+    timeout(READY, ServiceExt::<&[u8]>::ready(&mut svc))
+        .await
+        .unwrap()
+        .unwrap();
+}
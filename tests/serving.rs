@@ -0,0 +1,161 @@
+mod common;
+
+use common::{at, queued};
+use elevator::car;
+use elevator::command_channel::{self, COMMAND_CHANNEL_CAPACITY};
+use elevator::config::ControllerConfig;
+use elevator::context::{Call, ElevatorContext};
+use elevator::position::Position;
+use elevator::strategies::scan::ScanStrategy;
+use elevator::transition::State;
+use elevator::types::cmd::Command;
+use elevator::types::event::Event;
+use elevator::types::sched_events::{Action, ScheduleEvent};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tower::{Service, ServiceExt};
+
+/// The car has stopped at `floor` and opens its door there.
+fn arrive(ctx: &mut ElevatorContext, floor: u8) {
+    ctx.position = Position::Stopped(floor);
+    ctx.active_target = Some(floor);
+    ctx.serve(floor);
+}
+
+#[test]
+fn calls_at_one_floor_share_a_stop() {
+    let mut ctx = at(1, true);
+    ctx.enqueue_call(3, Call::Car);
+    ctx.enqueue_call(3, Call::HallUp);
+    ctx.enqueue_call(5, Call::Car);
+    assert_eq!(queued(&ctx), (vec![3, 5], vec![]));
+
+    arrive(&mut ctx, 3);
+    assert_eq!(queued(&ctx), (vec![5], vec![]));
+    assert!(ctx.calls.iter().all(|&(f, _)| f == 5));
+    assert_eq!(ctx.lamps_to_clear, [(3, Call::Car), (3, Call::HallUp)]);
+}
+
+#[test]
+fn hall_call_for_the_other_direction_waits_for_its_sweep() {
+    let mut ctx = at(1, true);
+    ctx.enqueue_call(3, Call::HallUp);
+    ctx.enqueue_call(3, Call::HallDown);
+    ctx.enqueue_call(5, Call::Car);
+    assert_eq!(queued(&ctx), (vec![3, 5], vec![3]));
+
+    arrive(&mut ctx, 3);
+    assert_eq!(queued(&ctx), (vec![5], vec![3]));
+    assert!(ctx.calls.contains(&(3, Call::HallDown)));
+    assert_eq!(
+        ctx.lamps_to_clear,
+        [(3, Call::HallUp)],
+        "its lamp stays lit"
+    );
+}
+
+#[test]
+fn turning_around_keeps_the_call_for_the_way_it_came() {
+    let mut ctx = at(1, true);
+    ctx.enqueue_call(3, Call::HallUp);
+    ctx.enqueue_call(3, Call::HallDown);
+    ctx.enqueue_call(2, Call::HallDown);
+
+    arrive(&mut ctx, 3);
+    assert!(!ctx.direction_up, "leaves down for floor 2");
+    assert_eq!(queued(&ctx), (vec![3], vec![2]));
+    assert!(ctx.calls.contains(&(3, Call::HallUp)));
+    assert_eq!(ctx.lamps_to_clear, [(3, Call::HallDown)]);
+    assert_eq!(ctx.next_target(), Some(2));
+}
+
+#[test]
+fn car_with_nowhere_to_go_serves_both_directions() {
+    let mut ctx = at(1, true);
+    ctx.enqueue_call(3, Call::HallUp);
+    ctx.enqueue_call(3, Call::HallDown);
+
+    arrive(&mut ctx, 3);
+    assert_eq!(queued(&ctx), (vec![], vec![]));
+    assert!(ctx.calls.is_empty());
+    assert_eq!(ctx.lamps_to_clear, [(3, Call::HallUp), (3, Call::HallDown)]);
+}
+
+#[test]
+fn passed_car_call_is_not_visited_again() {
+    // Pressed while the car was above floor 3, then the hall call on the way up.
+    let mut ctx = at(5, false);
+    ctx.enqueue_call(3, Call::Car);
+    ctx.position = Position::Stopped(2);
+    ctx.direction_up = true;
+    ctx.enqueue_call(3, Call::HallUp);
+    ctx.enqueue_call(4, Call::Car);
+    assert_eq!(queued(&ctx), (vec![3, 4], vec![3]));
+
+    arrive(&mut ctx, 3);
+    assert_eq!(queued(&ctx), (vec![4], vec![]));
+}
+
+#[test]
+fn scan_serves_the_floor_when_the_door_opens() {
+    let mut ctx = at(3, true);
+    ScanStrategy::plan(Event::ElevatorDown(3), &mut ctx, State::Idle);
+    ScanStrategy::plan(Event::PanelButtonPressed(3), &mut ctx, State::DoorOpening);
+    assert_eq!(ctx.active_target, Some(3));
+
+    let plan = ScanStrategy::plan(Event::DoorOpened(3), &mut ctx, State::DoorOpening)
+        .plan
+        .unwrap();
+    assert_eq!(queued(&ctx), (vec![], vec![]));
+    assert!(ctx.calls.is_empty());
+    assert!(matches!(
+        plan.steps().next().map(|step| &step.event),
+        Some(ScheduleEvent::Instant(Action::ClearingLamps))
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn opening_the_door_clears_only_the_lamps_of_served_calls() {
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let (tx, rx) = command_channel::channel(COMMAND_CHANNEL_CAPACITY);
+    let log = batches.clone();
+    command_channel::spawn_loopback(rx, move |batch| log.lock().unwrap().push(batch));
+    let mut ctx = at(3, true);
+    ctx.enqueue_call(3, Call::Car);
+    ctx.enqueue_call(3, Call::HallDown);
+    ctx.enqueue_call(5, Call::Car);
+    ctx.next_target();
+    let car = car::spawn(State::Idle.enter(tx), ctx);
+    let (mut scheduler, _) =
+        common::scheduler(ScanStrategy::new(), &car, ControllerConfig::default());
+
+    car.apply(Action::OpeningDoor, true).await.unwrap();
+    scheduler
+        .ready()
+        .await
+        .unwrap()
+        .call(Event::DoorOpened(3))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert_eq!(
+        *batches.lock().unwrap(),
+        [vec![Command::CP(3), Command::DO], vec![Command::DC]],
+        "the lamps go out with the door in one datagram; the down call waits \
+         for the car's way back, lit"
+    );
+}
+
+#[tokio::test]
+async fn lamps_stay_to_clear_when_the_send_fails() {
+    let (tx, rx) = command_channel::channel(COMMAND_CHANNEL_CAPACITY);
+    drop(rx);
+    let mut ctx = at(3, true);
+    ctx.lamps_to_clear.push((3, Call::Car));
+    let car = car::spawn(State::DoorOpened.enter(tx), ctx);
+
+    let applied = car.apply(Action::ClearingLamps, true).await.unwrap();
+    assert!(applied.result.is_err());
+    let snapshot = car.snapshot().await.unwrap();
+    assert_eq!(snapshot.context.lamps_to_clear, [(3, Call::Car)]);
+}
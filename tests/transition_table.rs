@@ -0,0 +1,59 @@
+mod common;
+
+use elevator::context::ElevatorContext;
+use elevator::position::Position;
+use elevator::transition::State;
+use elevator::transition_table::{TRANSITIONS, next_state, to_dot, to_mermaid};
+use elevator::types::sched_events::Action;
+
+#[tokio::test]
+async fn typestates_match_transition_table() {
+    // An overloaded car follows the table too.
+    for load in [0, 120] {
+        for state in State::ALL {
+            for action in Action::ALL {
+                let (tx, _) = common::loopback();
+                let mut ctx = ElevatorContext {
+                    position: Position::Stopped(3),
+                    min_floor: 1,
+                    max_floor: 5,
+                    load,
+                    ..Default::default()
+                };
+                let machine = state.enter(tx);
+                assert_eq!(machine.state(), state);
+
+                let next = machine.on_event(action, &mut ctx).await.unwrap();
+                let expected = next_state(state, action).unwrap_or(state);
+                assert_eq!(
+                    next.state(),
+                    expected,
+                    "{state:?} on {action:?} at {load}% went to {:?}",
+                    next.state()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn table_has_no_duplicate_edges() {
+    for (i, (from, action, _)) in TRANSITIONS.iter().enumerate() {
+        assert!(
+            !TRANSITIONS[i + 1..]
+                .iter()
+                .any(|(f, a, _)| f == from && a == action),
+            "duplicate edge for {from:?} on {action:?}"
+        );
+    }
+}
+
+#[test]
+fn exports_contain_every_edge() {
+    let dot = to_dot();
+    let mermaid = to_mermaid();
+    for (from, action, to) in TRANSITIONS {
+        assert!(dot.contains(&format!("{from:?} -> {to:?} [label=\"{action:?}\"]")));
+        assert!(mermaid.contains(&format!("{from:?} --> {to:?}: {action:?}")));
+    }
+}